@@ -9,7 +9,7 @@ start_time = time.time()
 
 for x in range(100):
                
-    os.system('cargo run')
+    os.system('cargo run --features demo')
     
 
 end_time = time.time()
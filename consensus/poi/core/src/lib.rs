@@ -0,0 +1,1290 @@
+//! Proof-of-Interaction primitives shared by the node, the consensus crate and the demo binary.
+//!
+//! A node `u0` wanting to push a block evaluates the sr25519 VRF of its key over the chain and
+//! the dependency to get `s0` ([`verify_seed`]), which seeds both the subset of service nodes to
+//! visit ([`create_services`]) and the number of hops of the tour ([`tour_length`]). As the VRF
+//! output is unique for a key, a dependency and an attempt, of which `u0` has [`MAX_ATTEMPTS`],
+//! `u0` can only draw a few times for a shorter tour or other services, and can still get past
+//! an offline service. [`generate_poi`] walks the tour and [`check_poi`] replays it to validate a
+//! [`PoiProof`]. Both draw the tour from the same [`PoiParameters`], which the chain holds.
+//!
+//! Every signature of a proof is an sr25519 signature, in `H512` format, made by the PoI session
+//! key of its signer ([`KEY_TYPE`]), which authorities use for nothing else, and so is the VRF
+//! output `s0` is derived from. Services only ever
+//! sign a [`Challenge`], tagged with [`CHALLENGE_DOMAIN`] and bound to the chain, the parent
+//! block and the hop it is made for. The tour is walked with Blake2-256 hashes, and its services
+//! and length are drawn from ChaCha20 streams with integer arithmetic only, so every node agrees
+//! on it whatever its platform or toolchain. `docs/poi-tour-spec.md` specifies the tour for
+//! other implementations, and `vectors/` holds test vectors they can check against.
+//!
+//! The crate is `no_std` without its default `std` feature, so proofs can also be checked by
+//! the runtime. Generation needs the `async` feature, and the demo binary the `demo` feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+#[cfg(feature = "async")]
+use core::{future::Future, time::Duration};
+#[cfg(feature = "async")]
+use futures::future::{select, Either};
+#[cfg(feature = "async")]
+use futures_timer::Delay;
+use merlin::Transcript;
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use scale_info::TypeInfo;
+use schnorrkel::vrf::{VRFOutput, VRFProof};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_application_crypto::KeyTypeId;
+pub use sp_arithmetic::{FixedPointNumber, FixedU64};
+#[cfg(feature = "std")]
+use sp_core::Pair;
+use sp_core::{bounded::BoundedVec, hashing::blake2_256, sr25519, ConstU32, H256, H512, U256};
+
+/// A node of the network set N, identified by its PoI key. `ip` is
+/// where it is reached, if known.
+pub struct Node {
+	name: String,
+	ip: String,
+	public_key: sr25519::Public,
+}
+
+/// Key type of the session key authorities sign PoI challenges with.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"poi_");
+
+mod app {
+	use sp_application_crypto::{app_crypto, sr25519};
+	app_crypto!(sr25519, super::KEY_TYPE);
+}
+
+sp_application_crypto::with_pair! {
+	/// A PoI key pair.
+	pub type PoiPair = app::Pair;
+}
+
+/// The PoI key of an authority.
+pub type PoiId = app::Public;
+
+/// A signature made with a PoI key.
+pub type PoiSignature = app::Signature;
+
+impl Node {
+	pub fn new(name: String, ip: String, public_key: sr25519::Public) -> Node {
+		Node { name, ip, public_key }
+	}
+	pub fn get_name(&self) -> &str {
+		&self.name
+	}
+	pub fn get_ip(&self) -> &str {
+		&self.ip
+	}
+	pub fn get_public_key(&self) -> &sr25519::Public {
+		&self.public_key
+	}
+}
+
+/// Maximum number of hops a proof can carry, which bounds the size of an
+/// encoded proof to about 128 KiB.
+pub const MAX_HOPS: u32 = 1024;
+
+/// A hop of the tour: the signature of the challenge by the visited
+/// service, and the counter-signature of that signature by u0.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct Hop {
+	pub signature: H512,
+	pub counter_signature: H512,
+}
+
+/// Attempts u0 may make at sealing a child of a given dependency, each
+/// drawing its own s0. A tour visiting an offline service can thus be
+/// started again over other services, while u0 can only draw a few times
+/// for a shorter tour.
+pub const MAX_ATTEMPTS: u32 = 4;
+
+/// The VRF output of u0 over the genesis hash, the dependency and the
+/// attempt, and the proof that u0 computed it, from which s0 is derived.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct PoiSeed {
+	pub attempt: u32,
+	pub output: H256,
+	pub proof: H512,
+}
+
+/// The proof P = {s0, s1, s1', .., sk, sk'} of a tour computed over
+/// `dependency` and `message`, s0 being carried as the seed it is derived
+/// from.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct PoiProof {
+	pub dependency: H256,
+	pub message: H256,
+	pub seed: PoiSeed,
+	pub hops: BoundedVec<Hop, ConstU32<MAX_HOPS>>,
+}
+
+impl PoiProof {
+	/// A proof without any hop yet.
+	pub fn new(dependency: H256, message: H256, seed: PoiSeed) -> PoiProof {
+		PoiProof { dependency, message, seed, hops: BoundedVec::default() }
+	}
+
+	/// Appends a hop, handing it back if the proof already holds MAX_HOPS.
+	pub fn push_hop(&mut self, hop: Hop) -> Result<(), Hop> {
+		self.hops.try_push(hop)
+	}
+}
+
+/// Tag prefixed to every challenge a service signs, so that a PoI
+/// signature can never be mistaken for a signature of anything else.
+pub const CHALLENGE_DOMAIN: &[u8] = b"ambula/poi/challenge/1";
+
+/// The values a service signs at a hop of the tour.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Challenge {
+	/// Hash of the genesis block of the chain the tour is made for.
+	pub genesis_hash: H256,
+	/// Hash of the block the sealed block builds on, the dependency.
+	pub parent_hash: H256,
+	/// Index of the hop in the tour, starting at 0.
+	pub hop: u32,
+	/// The hash reached by the tour so far.
+	pub current_hash: H256,
+}
+
+impl Challenge {
+	/// The bytes the service signs.
+	pub fn to_message(&self) -> Vec<u8> {
+		hop_challenge(&self.genesis_hash, &self.parent_hash, self.hop, &self.current_hash)
+	}
+}
+
+/// The key u0 draws s0 and signs the counter-signatures with.
+pub trait TourSigner {
+	/// The public key the signatures verify against.
+	fn key(&self) -> sr25519::Public;
+	/// Signs `message`, None if the key is not available.
+	fn sign_message(&self, message: &[u8]) -> Option<H512>;
+	/// Evaluates the VRF over
+	/// seed_transcript(genesis_hash, dependency, attempt), None if the key
+	/// is not available.
+	fn vrf_seed(&self, genesis_hash: &H256, dependency: &H256, attempt: u32) -> Option<PoiSeed>;
+}
+
+#[cfg(feature = "std")]
+impl TourSigner for sr25519::Pair {
+	fn key(&self) -> sr25519::Public {
+		self.public()
+	}
+	fn sign_message(&self, message: &[u8]) -> Option<H512> {
+		Some(H512::from(self.sign(message).0))
+	}
+	fn vrf_seed(&self, genesis_hash: &H256, dependency: &H256, attempt: u32) -> Option<PoiSeed> {
+		let keypair: &schnorrkel::Keypair = self.as_ref();
+		let (inout, proof, _) =
+			keypair.vrf_sign(seed_transcript(genesis_hash, dependency, attempt));
+		Some(PoiSeed {
+			attempt,
+			output: H256::from(inout.to_output().to_bytes()),
+			proof: H512::from(proof.to_bytes()),
+		})
+	}
+}
+
+/// Why generating a proof failed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PoiError {
+	/// A service could not be reached, or did not answer in time.
+	PeerUnreachable,
+	/// A service answered with a signature that does not verify.
+	BadSignature,
+	/// A service answered with something else than a signature.
+	MalformedResponse,
+	/// A value needed to reach a service, such as its address, could not be parsed.
+	ParseFailure,
+	/// u0 could not sign with its key.
+	SigningFailed,
+	/// The network is too small to draw services from, or could not be built.
+	NotEnoughNodes,
+	/// The tour is longer than a proof can hold.
+	TourTooLong,
+	/// The attempt was abandoned, as the parent block changed.
+	Aborted,
+	/// u0 made all of its MAX_ATTEMPTS attempts on the dependency already.
+	NoAttemptLeft,
+}
+
+impl PoiError {
+	/// Whether asking the same service again may succeed.
+	pub fn is_transient(&self) -> bool {
+		matches!(
+			self,
+			PoiError::PeerUnreachable | PoiError::BadSignature | PoiError::MalformedResponse
+		)
+	}
+}
+
+impl fmt::Display for PoiError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description: &str = match self {
+			PoiError::PeerUnreachable => "service unreachable",
+			PoiError::BadSignature => "service answered with an invalid signature",
+			PoiError::MalformedResponse => "service answered with a malformed response",
+			PoiError::ParseFailure => "failed to parse the address of a service",
+			PoiError::SigningFailed => "failed to sign with the initiator key",
+			PoiError::NotEnoughNodes => "not enough nodes to draw services from",
+			PoiError::TourTooLong => "tour longer than a proof can hold",
+			PoiError::Aborted => "aborted as the parent block changed",
+			PoiError::NoAttemptLeft => "no attempt left on the parent block",
+		};
+		f.write_str(description)
+	}
+}
+
+/// How generate_poi deals with services failing to answer.
+#[cfg(feature = "async")]
+#[derive(Clone, Debug)]
+pub struct TourConfig {
+	/// Time a service is given to answer.
+	pub hop_timeout: Duration,
+	/// Times a service failing to answer is asked again before giving up.
+	pub max_retries: u32,
+}
+
+#[cfg(feature = "async")]
+impl Default for TourConfig {
+	fn default() -> Self {
+		TourConfig { hop_timeout: Duration::from_secs(5), max_retries: 2 }
+	}
+}
+
+/// The knobs of the tours. They are set on chain, so that the node
+/// generating a proof and the nodes checking it read the same values at
+/// the parent block.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PoiParameters {
+	/// Maximum number of services a tour is drawn from, which never
+	/// exceeds half of the network.
+	pub subset_size: u32,
+	/// Standard deviation of the tour length, per node of the network.
+	pub std_deviation_coefficient: FixedU64,
+	/// Minimum tour length, per node of the network.
+	pub min_tour_length: FixedU64,
+	/// Mean tour length.
+	pub difficulty: FixedU64,
+	/// Distribution the tour length is drawn from.
+	pub distribution: TourLengthDistribution,
+}
+
+/// The distributions the tour length can be drawn from, see tour_length.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum TourLengthDistribution {
+	/// Normal around the difficulty, with the standard deviation of the
+	/// parameters.
+	Normal,
+	/// Geometric of mean the difficulty, whatever the standard deviation.
+	Geometric,
+	/// Uniform around the difficulty, with the standard deviation of the
+	/// parameters.
+	Uniform,
+}
+
+/// Label of the VRF transcript s0 is drawn from, and context s0 is
+/// derived from the VRF output with.
+pub const SEED_DOMAIN: &[u8] = b"ambula/poi/seed/1";
+
+/// Tag prefixed to s0 to key the stream the tour length is drawn from.
+pub const TOUR_LENGTH_DOMAIN: &[u8] = b"ambula/poi/tour-length/1";
+
+/// Tag prefixed to s0 to key the stream the services are drawn from.
+pub const SERVICES_DOMAIN: &[u8] = b"ambula/poi/services/1";
+
+/// 1 in the fixed-point format of the parameters, that of FixedU64
+const FIXED_ONE: u128 = 1_000_000_000;
+/// The square root of 3 in the fixed-point format of the parameters
+const SQRT_3: u128 = 1_732_050_808;
+
+impl PoiParameters {
+	/// The lowest value each parameter may take. Any distribution may be
+	/// used.
+	pub const MIN: PoiParameters = PoiParameters {
+		subset_size: 1,
+		std_deviation_coefficient: FixedU64::from_inner(0),
+		min_tour_length: FixedU64::from_inner(0),
+		difficulty: FixedU64::from_inner(1_000_000_000),
+		distribution: TourLengthDistribution::Normal,
+	};
+
+	/// The highest value each parameter may take.
+	pub const MAX: PoiParameters = PoiParameters {
+		subset_size: 256,
+		std_deviation_coefficient: FixedU64::from_inner(1_000_000_000),
+		min_tour_length: FixedU64::from_inner(4_000_000_000),
+		difficulty: FixedU64::from_inner(MAX_HOPS as u64 * 1_000_000_000),
+		distribution: TourLengthDistribution::Normal,
+	};
+
+	/// Whether every parameter lies within its bounds.
+	pub fn is_valid(&self) -> bool {
+		(Self::MIN.subset_size..=Self::MAX.subset_size).contains(&self.subset_size) &&
+			(Self::MIN.std_deviation_coefficient..=Self::MAX.std_deviation_coefficient)
+				.contains(&self.std_deviation_coefficient) &&
+			(Self::MIN.min_tour_length..=Self::MAX.min_tour_length)
+				.contains(&self.min_tour_length) &&
+			(Self::MIN.difficulty..=Self::MAX.difficulty).contains(&self.difficulty)
+	}
+
+	/// Whether every parameter lies within its bounds and the minimum tour
+	/// length fits in a proof for networks of up to `max_network_size`
+	/// nodes, the minimum growing with the network.
+	pub fn is_valid_for(&self, max_network_size: u32) -> bool {
+		self.is_valid() && self.min_length(u64::from(max_network_size)) <= u64::from(MAX_HOPS)
+	}
+
+	/// The minimum tour length `floor(min_tour_length * n)` for a network
+	/// of n nodes.
+	pub fn min_length(&self, network_size: u64) -> u64 {
+		to_hops_floor(u128::from(self.min_tour_length.into_inner()) * u128::from(network_size))
+	}
+}
+
+impl Default for PoiParameters {
+	/// The values of the paper: at most 20 services, a minimum length of
+	/// the network size, and a normal distribution of mean 20 whose
+	/// standard deviation is a tenth of the network size.
+	fn default() -> Self {
+		PoiParameters {
+			subset_size: 20,
+			std_deviation_coefficient: FixedU64::from_inner(100_000_000),
+			min_tour_length: FixedU64::from_inner(1_000_000_000),
+			difficulty: FixedU64::from_inner(20_000_000_000),
+			distribution: TourLengthDistribution::Normal,
+		}
+	}
+}
+
+/// The algorithm createServices of the paper: the services of the tour
+/// seeded with `s0`, drawn by select_services from `network_nodes`, the
+/// network set N sorted by key, in the order they were drawn.
+pub fn create_services<'a>(
+	s0: &H512,
+	subset_size: u32,
+	network_nodes: &'a [Node],
+) -> Vec<&'a Node> {
+	select_services(s0, subset_size, network_nodes.len())
+		.into_iter()
+		.map(|index| &network_nodes[index])
+		.collect()
+}
+
+/// Draws the indices of the services among the `network_size` nodes of
+/// the network set N with a partial Fisher-Yates shuffle.
+///
+/// The k = min(subset_size, floor(n / 2)) services are drawn from the
+/// ChaCha20 stream keyed with the Blake2-256 hash of SERVICES_DOMAIN
+/// followed by s0. Starting from the list [0, 1, ..., n - 1], for i from
+/// 0 to k - 1, the item at i is swapped with the item at
+/// i + uniform_below(n - i). The services are the first k items, in that
+/// order. See docs/poi-tour-spec.md.
+pub fn select_services(s0: &H512, subset_size: u32, network_size: usize) -> Vec<usize> {
+	let mut rng: ChaCha20Rng = keyed_rng(SERVICES_DOMAIN, s0);
+	let count: usize = (subset_size as usize).min(network_size / 2);
+	let mut indices: Vec<usize> = (0..network_size).collect();
+	for i in 0..count {
+		let j: usize = i + uniform_below(&mut rng, (network_size - i) as u64) as usize;
+		indices.swap(i, j);
+	}
+	indices.truncate(count);
+	indices
+}
+
+/// The ChaCha20 stream keyed with the hash of `domain` followed by s0.
+fn keyed_rng(domain: &[u8], s0: &H512) -> ChaCha20Rng {
+	let mut seed: Vec<u8> = domain.to_vec();
+	seed.extend_from_slice(s0.as_bytes());
+	ChaCha20Rng::from_seed(blake2_256(&seed))
+}
+
+/// The number of hops of the tour seeded with s0, over a network of
+/// `network_size` nodes, drawn from the distribution of `parameters`.
+///
+/// The length is drawn with integer arithmetic only, from the ChaCha20
+/// stream keyed with the Blake2-256 hash of TOUR_LENGTH_DOMAIN followed by
+/// s0 (64-bit block counter and stream id both starting at 0, words read
+/// in order, a u64 being made of two words, low word first). Values are
+/// fixed-point numbers scaled by 10^9 like FixedU64, with the mean
+/// `difficulty`, the standard deviation `std_deviation_coefficient * n`
+/// and the minimum `floor(min_tour_length * n)` for a network of n nodes.
+/// A fixed-point value is rounded to the nearest number of hops, halves
+/// rounding up. The length is then:
+///
+/// - Normal: mean + deviation * Z, Z being the sum of 12 u32 draws divided by 2^32, minus 6
+///   (Irwin-Hall), negative values being 0.
+/// - Geometric: the number of u64 draws up to and including the first one below floor(2^64 / mean),
+///   which happens with probability 1 / mean, counting at most MAX_HOPS + 1 draws.
+/// - Uniform: a number of hops drawn with uniform_below between the rounded mean - sqrt(3) *
+///   deviation and mean + sqrt(3) * deviation, both included, sqrt(3) being 1.732050808.
+pub fn tour_length(s0: &H512, parameters: &PoiParameters, network_size: u64) -> u64 {
+	let mut rng: ChaCha20Rng = keyed_rng(TOUR_LENGTH_DOMAIN, s0);
+	let mean: u128 = u128::from(parameters.difficulty.into_inner());
+	let deviation: u128 =
+		u128::from(parameters.std_deviation_coefficient.into_inner()) * u128::from(network_size);
+	let min_length: u64 = parameters.min_length(network_size);
+	let length: u64 = match parameters.distribution {
+		TourLengthDistribution::Normal => sample_normal(&mut rng, mean, deviation),
+		TourLengthDistribution::Geometric => sample_geometric(&mut rng, mean),
+		TourLengthDistribution::Uniform => sample_uniform(&mut rng, mean, deviation),
+	};
+	length.max(min_length)
+}
+
+/// Samples the normal distribution as the Irwin-Hall sum of 12 uniform
+/// fractions, whose mean is 6 and variance 1.
+fn sample_normal(rng: &mut ChaCha20Rng, mean: u128, deviation: u128) -> u64 {
+	let sum: i128 = (0..12).map(|_| i128::from(rng.next_u32())).sum();
+	// Z scaled by 2^32
+	let z: i128 = sum - (6 << 32);
+	let value: i128 = mean as i128 + ((deviation as i128 * z) >> 32);
+	to_hops(value.max(0) as u128)
+}
+
+/// Samples the geometric distribution of mean `mean` by counting Bernoulli
+/// trials up to the first success.
+fn sample_geometric(rng: &mut ChaCha20Rng, mean: u128) -> u64 {
+	let threshold: u128 = (FIXED_ONE << 64) / mean.max(FIXED_ONE);
+	let mut trials: u64 = 1;
+	while u128::from(rng.next_u64()) >= threshold && trials <= u64::from(MAX_HOPS) {
+		trials += 1;
+	}
+	trials
+}
+
+/// Samples the uniform distribution whose standard deviation is
+/// `deviation`, around `mean`.
+fn sample_uniform(rng: &mut ChaCha20Rng, mean: u128, deviation: u128) -> u64 {
+	let spread: u128 = deviation * SQRT_3 / FIXED_ONE;
+	let low: u64 = to_hops(mean.saturating_sub(spread));
+	let high: u64 = to_hops(mean + spread);
+	low + uniform_below(rng, high - low + 1)
+}
+
+/// Draws a number uniformly in [0, bound), `bound` not being 0.
+/// A u64 is drawn until it falls below the largest multiple of `bound`
+/// not exceeding 2^64, and reduced modulo `bound`, so that no value is
+/// favoured.
+pub fn uniform_below<R: RngCore>(rng: &mut R, bound: u64) -> u64 {
+	let zone: u128 = ((1 << 64) / u128::from(bound)) * u128::from(bound);
+	loop {
+		let value: u64 = rng.next_u64();
+		if u128::from(value) < zone {
+			return value % bound;
+		}
+	}
+}
+
+/// Rounds a fixed-point value to the nearest number of hops.
+fn to_hops(value: u128) -> u64 {
+	to_hops_floor(value + FIXED_ONE / 2)
+}
+
+/// Rounds a fixed-point value down to a number of hops.
+fn to_hops_floor(value: u128) -> u64 {
+	(value / FIXED_ONE).min(u128::from(u64::MAX)) as u64
+}
+
+/// Whether `signature` is an sr25519 signature of `message` by `u`.
+pub fn verify_signature(u: &sr25519::Public, signature: &H512, message: &[u8]) -> bool {
+	let signature: sr25519::Signature = sr25519::Signature::from_raw(signature.to_fixed_bytes());
+	sp_io::crypto::sr25519_verify(&signature, message, u)
+}
+
+/// The VRF transcript, labelled SEED_DOMAIN, u0 draws s0 from at its
+/// `attempt` to seal a child of `dependency` on the chain of genesis
+/// `genesis_hash`.
+pub fn seed_transcript(genesis_hash: &H256, dependency: &H256, attempt: u32) -> Transcript {
+	let mut transcript: Transcript = Transcript::new(SEED_DOMAIN);
+	transcript.append_message(b"genesis_hash", genesis_hash.as_bytes());
+	transcript.append_message(b"dependency", dependency.as_bytes());
+	transcript.append_u64(b"attempt", u64::from(attempt));
+	transcript
+}
+
+/// Checks that `seed` is the VRF output of u over
+/// seed_transcript(genesis_hash, dependency, attempt) for an attempt
+/// below MAX_ATTEMPTS, and derives s0 from it, None if the seed is not
+/// valid. A key has a single VRF output per transcript, so s0 is the same
+/// whenever u starts a given attempt again.
+pub fn verify_seed(
+	u: &sr25519::Public,
+	seed: &PoiSeed,
+	genesis_hash: &H256,
+	dependency: &H256,
+) -> Option<H512> {
+	if seed.attempt >= MAX_ATTEMPTS {
+		return None;
+	}
+
+	let public: schnorrkel::PublicKey = schnorrkel::PublicKey::from_bytes(&u.0).ok()?;
+	let output: VRFOutput = VRFOutput::from_bytes(seed.output.as_bytes()).ok()?;
+	let proof: VRFProof = VRFProof::from_bytes(seed.proof.as_bytes()).ok()?;
+	let (inout, _) = public
+		.vrf_verify(seed_transcript(genesis_hash, dependency, seed.attempt), &output, &proof)
+		.ok()?;
+	Some(inout.make_bytes::<H512>(SEED_DOMAIN))
+}
+
+/// The Blake2-256 hash of `value`.
+pub fn hash(value: &[u8]) -> H256 {
+	H256::from(blake2_256(value))
+}
+
+/// The hash a tour over `message` starts from, that of s0 followed by
+/// `message`.
+pub fn tour_start(s0: &H512, message: &H256) -> H256 {
+	let mut data_to_hash: Vec<u8> = s0.as_bytes().to_vec();
+	data_to_hash.extend_from_slice(message.as_bytes());
+	hash(&data_to_hash)
+}
+
+/// The index, among `services_count` services, of the service visited
+/// after the tour reached `current_hash`.
+pub fn next_hop(current_hash: &H256, services_count: usize) -> usize {
+	let current: U256 = U256::from_big_endian(current_hash.as_bytes());
+	(current % U256::from(services_count)).low_u64() as usize
+}
+
+/// The message a service signs at hop `hop` of a tour which reached
+/// `current_hash`, to seal a child of `dependency` on the chain of genesis
+/// `genesis_hash`: CHALLENGE_DOMAIN followed by the SCALE encoding of the
+/// four values.
+pub fn hop_challenge(
+	genesis_hash: &H256,
+	dependency: &H256,
+	hop: u32,
+	current_hash: &H256,
+) -> Vec<u8> {
+	let mut challenge: Vec<u8> = CHALLENGE_DOMAIN.to_vec();
+	(genesis_hash, dependency, hop, current_hash).encode_to(&mut challenge);
+	challenge
+}
+
+/// The services of the tour seeded with s0 and its length, drawn with
+/// `parameters` from the network set N.
+fn plan_tour<'a>(
+	s0: &H512,
+	parameters: &PoiParameters,
+	network_nodes: &'a [Node],
+) -> (Vec<&'a Node>, u64) {
+	let services: Vec<&Node> = create_services(s0, parameters.subset_size, network_nodes);
+	let length: u64 = tour_length(s0, parameters, network_nodes.len() as u64);
+	(services, length)
+}
+
+/// Whether `proof` is a valid proof of interaction of `signer_key` on the
+/// chain of genesis `genesis_hash`, `parameters` being those of the tours
+/// at the dependency and `network_nodes` the network set N.
+pub fn check_poi(
+	proof: &PoiProof,
+	genesis_hash: &H256,
+	signer_key: &sr25519::Public,
+	parameters: &PoiParameters,
+	network_nodes: &[Node],
+) -> bool {
+	let s0: H512 = match verify_seed(signer_key, &proof.seed, genesis_hash, &proof.dependency) {
+		Some(s0) => s0,
+		None => return false,
+	};
+	let (services, length): (Vec<&Node>, u64) = plan_tour(&s0, parameters, network_nodes);
+
+	if services.is_empty() || length != proof.hops.len() as u64 {
+		return false;
+	}
+
+	let mut current_hash: H256 = tour_start(&s0, &proof.message);
+	for (index, hop) in proof.hops.iter().enumerate() {
+		let next_node_key: &sr25519::Public =
+			services[next_hop(&current_hash, services.len())].get_public_key();
+		let to_check: Vec<u8> =
+			hop_challenge(genesis_hash, &proof.dependency, index as u32, &current_hash);
+		if !verify_signature(next_node_key, &hop.signature, &to_check) {
+			return false;
+		}
+		if !verify_signature(signer_key, &hop.counter_signature, hop.signature.as_bytes()) {
+			return false;
+		}
+		current_hash = hash(hop.counter_signature.as_bytes())
+	}
+	true
+}
+
+/// Makes u0 walk the tour of its `attempt` over `message`, the pre-hash
+/// of a child of `dependency`, and returns the proof of interaction.
+/// `ask_signature` reaches a service node and asks it to sign the
+/// challenge of a hop. A service failing to answer in time, or without a
+/// valid signature, is asked again up to `config.max_retries` times.
+///
+/// Dropping the returned future abandons the attempt. As s0 is the VRF
+/// output of u0 over the dependency and the attempt, starting the same
+/// attempt again draws the same services and tour length, so a tour
+/// failing on a service should be followed by the next attempt, up to
+/// MAX_ATTEMPTS.
+#[cfg(feature = "async")]
+pub async fn generate_poi<S, F, Fut>(
+	u0: &S,
+	genesis_hash: H256,
+	dependency: H256,
+	attempt: u32,
+	message: H256,
+	parameters: &PoiParameters,
+	network_nodes: &[Node],
+	config: &TourConfig,
+	mut ask_signature: F,
+) -> Result<PoiProof, PoiError>
+where
+	S: TourSigner,
+	F: FnMut(&Node, Challenge) -> Fut,
+	Fut: Future<Output = Result<H512, PoiError>>,
+{
+	if attempt >= MAX_ATTEMPTS {
+		return Err(PoiError::NoAttemptLeft);
+	}
+
+	let seed: PoiSeed = u0
+		.vrf_seed(&genesis_hash, &dependency, attempt)
+		.ok_or(PoiError::SigningFailed)?;
+	let s0: H512 =
+		verify_seed(&u0.key(), &seed, &genesis_hash, &dependency).ok_or(PoiError::SigningFailed)?;
+	let (services, length): (Vec<&Node>, u64) = plan_tour(&s0, parameters, network_nodes);
+
+	if services.is_empty() {
+		return Err(PoiError::NotEnoughNodes);
+	}
+	if length > MAX_HOPS as u64 {
+		return Err(PoiError::TourTooLong);
+	}
+
+	let mut proof: PoiProof = PoiProof::new(dependency, message, seed);
+	let mut current_hash: H256 = tour_start(&s0, &message);
+	for k in 0..length {
+		let service: &Node = services[next_hop(&current_hash, services.len())];
+		let challenge: Challenge =
+			Challenge { genesis_hash, parent_hash: dependency, hop: k as u32, current_hash };
+		let sk: H512 = ask_hop(service, challenge, config, &mut ask_signature).await?;
+		let sk_prime: H512 = u0.sign_message(sk.as_bytes()).ok_or(PoiError::SigningFailed)?;
+		proof
+			.push_hop(Hop { signature: sk, counter_signature: sk_prime })
+			.map_err(|_| PoiError::TourTooLong)?;
+		current_hash = hash(sk_prime.as_bytes());
+	}
+	Ok(proof)
+}
+
+/// Asks `service` to sign `challenge`, checking the answer and retrying
+/// transient failures.
+#[cfg(feature = "async")]
+async fn ask_hop<F, Fut>(
+	service: &Node,
+	challenge: Challenge,
+	config: &TourConfig,
+	ask_signature: &mut F,
+) -> Result<H512, PoiError>
+where
+	F: FnMut(&Node, Challenge) -> Fut,
+	Fut: Future<Output = Result<H512, PoiError>>,
+{
+	let message: Vec<u8> = challenge.to_message();
+	let mut retries: u32 = 0;
+	loop {
+		let answer =
+			select(Box::pin(ask_signature(service, challenge)), Delay::new(config.hop_timeout))
+				.await;
+		let result: Result<H512, PoiError> = match answer {
+			Either::Left((Ok(sk), _))
+				if verify_signature(service.get_public_key(), &sk, &message) =>
+				Ok(sk),
+			Either::Left((Ok(_), _)) => Err(PoiError::BadSignature),
+			Either::Left((Err(error), _)) => Err(error),
+			Either::Right(_) => Err(PoiError::PeerUnreachable),
+		};
+		match result {
+			Err(error) if error.is_transient() && retries < config.max_retries => retries += 1,
+			result => return result,
+		}
+	}
+}
+
+// Test vectors: the tour must be walked the same way by every node, so
+// the hashing is pinned here.
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{hexdisplay::HexDisplay, Pair};
+	use std::str::FromStr;
+
+	fn h256(hex: &str) -> H256 {
+		H256::from_str(hex).unwrap()
+	}
+
+	#[test]
+	fn hash_is_blake2_256() {
+		assert_eq!(
+			hash(b""),
+			h256("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8")
+		);
+		assert_eq!(
+			hash(b"abc"),
+			h256("bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319")
+		);
+		assert_eq!(
+			hash(H512::repeat_byte(2).as_bytes()),
+			h256("bfed46301bf63819ba4dae77ae6077d7a9b3950210f30768acd2d110797f4adb")
+		);
+	}
+
+	#[test]
+	fn tour_starts_from_s0_and_message() {
+		let start: H256 = tour_start(&H512::repeat_byte(1), &H256::from_low_u64_be(999));
+		assert_eq!(start, h256("bed2920d4deb3f0aee554d5e8dca36b33566ee920aa64cdb2deb9b87a3fee796"));
+		assert_eq!(next_hop(&start, 6), 0);
+		assert_eq!(next_hop(&start, 20), 2);
+	}
+
+	#[test]
+	fn hop_challenge_is_tagged_scale() {
+		let challenge: Vec<u8> = hop_challenge(
+			&H256::repeat_byte(1),
+			&H256::from_low_u64_be(54321),
+			3,
+			&H256::repeat_byte(0xab),
+		);
+		assert_eq!(
+			format!("{}", HexDisplay::from(&challenge)),
+			concat!(
+				// "ambula/poi/challenge/1"
+				"616d62756c612f706f692f6368616c6c656e67652f31",
+				"0101010101010101010101010101010101010101010101010101010101010101",
+				"000000000000000000000000000000000000000000000000000000000000d431",
+				"03000000",
+				"abababababababababababababababababababababababababababababababab"
+			)
+		);
+	}
+
+	#[test]
+	fn challenge_signs_its_values() {
+		let challenge: Challenge = Challenge {
+			genesis_hash: H256::repeat_byte(1),
+			parent_hash: H256::repeat_byte(2),
+			hop: 7,
+			current_hash: H256::repeat_byte(3),
+		};
+		assert_eq!(
+			challenge.to_message(),
+			hop_challenge(&H256::repeat_byte(1), &H256::repeat_byte(2), 7, &H256::repeat_byte(3))
+		);
+		assert_ne!(challenge.to_message(), Challenge { hop: 8, ..challenge }.to_message());
+	}
+
+	#[test]
+	fn verifies_sr25519_signatures() {
+		let pair: sr25519::Pair = sr25519::Pair::from_seed(&[1; 32]);
+		let signature: H512 = H512::from(pair.sign(b"54321").0);
+
+		assert!(verify_signature(&pair.public(), &signature, b"54321"));
+		assert!(!verify_signature(&pair.public(), &signature, b"54322"));
+		assert!(!verify_signature(
+			&sr25519::Pair::from_seed(&[2; 32]).public(),
+			&signature,
+			b"54321"
+		));
+	}
+
+	#[test]
+	fn default_parameters_are_within_bounds() {
+		let parameters: PoiParameters = PoiParameters::default();
+		assert!(parameters.is_valid());
+		assert!(PoiParameters::MIN.is_valid());
+		assert!(PoiParameters::MAX.is_valid());
+
+		assert!(!PoiParameters { subset_size: 0, ..parameters }.is_valid());
+		assert!(!PoiParameters {
+			std_deviation_coefficient: FixedU64::saturating_from_rational(3, 2),
+			..parameters
+		}
+		.is_valid());
+		assert!(!PoiParameters {
+			min_tour_length: FixedU64::saturating_from_integer(5),
+			..parameters
+		}
+		.is_valid());
+		assert!(!PoiParameters {
+			difficulty: FixedU64::saturating_from_rational(1, 2),
+			..parameters
+		}
+		.is_valid());
+		assert!(!PoiParameters {
+			difficulty: FixedU64::saturating_from_integer(MAX_HOPS + 1),
+			..parameters
+		}
+		.is_valid());
+	}
+
+	#[test]
+	fn minimum_tour_length_must_fit_in_a_proof() {
+		// The bound of min_tour_length fits any network of up to 256 nodes
+		assert!(PoiParameters::MAX.is_valid_for(256));
+		assert!(!PoiParameters::MAX.is_valid_for(257));
+
+		// floor(2 * 512) = MAX_HOPS, floor((2 + 1/512) * 512) = MAX_HOPS + 1
+		let parameters: PoiParameters = PoiParameters {
+			min_tour_length: FixedU64::saturating_from_integer(2),
+			..Default::default()
+		};
+		assert_eq!(parameters.min_length(512), u64::from(MAX_HOPS));
+		assert!(parameters.is_valid_for(512));
+		let parameters: PoiParameters =
+			PoiParameters { min_tour_length: FixedU64::from_inner(2_001_953_125), ..parameters };
+		assert_eq!(parameters.min_length(512), u64::from(MAX_HOPS) + 1);
+		assert!(!parameters.is_valid_for(512));
+		assert!(parameters.is_valid_for(511));
+	}
+
+	// Golden vectors of the tour length, which generate_poi and check_poi
+	// must both draw: the lengths drawn from s0 = [b; 64] for b = 1, 2, 3.
+	fn tour_lengths(parameters: &PoiParameters, network_size: u64) -> Vec<u64> {
+		(1..=3)
+			.map(|b| tour_length(&H512::repeat_byte(b), parameters, network_size))
+			.collect()
+	}
+
+	fn wide_parameters(distribution: TourLengthDistribution) -> PoiParameters {
+		PoiParameters {
+			std_deviation_coefficient: FixedU64::saturating_from_rational(1, 2),
+			min_tour_length: FixedU64::from_inner(0),
+			difficulty: FixedU64::saturating_from_integer(100),
+			distribution,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn tour_length_golden_vectors() {
+		let with = |distribution| PoiParameters { distribution, ..Default::default() };
+
+		assert_eq!(tour_lengths(&with(TourLengthDistribution::Normal), 6), vec![20, 19, 19]);
+		assert_eq!(tour_lengths(&with(TourLengthDistribution::Geometric), 6), vec![13, 37, 6]);
+		assert_eq!(tour_lengths(&with(TourLengthDistribution::Uniform), 6), vec![20, 20, 20]);
+
+		assert_eq!(
+			tour_lengths(&wide_parameters(TourLengthDistribution::Normal), 20),
+			vec![99, 77, 91]
+		);
+		assert_eq!(
+			tour_lengths(&wide_parameters(TourLengthDistribution::Geometric), 20),
+			vec![87, 65, 376]
+		);
+		assert_eq!(
+			tour_lengths(&wide_parameters(TourLengthDistribution::Uniform), 20),
+			vec![88, 106, 103]
+		);
+	}
+
+	#[test]
+	fn tour_length_is_at_least_the_minimum() {
+		// The minimum is the network size by default
+		assert_eq!(tour_lengths(&PoiParameters::default(), 100), vec![100, 100, 100]);
+
+		let parameters: PoiParameters = PoiParameters {
+			min_tour_length: FixedU64::saturating_from_rational(3, 2),
+			..wide_parameters(TourLengthDistribution::Normal)
+		};
+		assert_eq!(tour_lengths(&parameters, 101), vec![151, 151, 151]);
+	}
+
+	#[derive(serde::Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct ServicesVector {
+		s0: H512,
+		subset_size: u32,
+		network_size: usize,
+		services: Vec<usize>,
+	}
+
+	#[test]
+	fn services_match_the_test_vectors() {
+		let vectors: Vec<ServicesVector> =
+			serde_json::from_str(include_str!("../vectors/services.json")).unwrap();
+		for vector in vectors {
+			assert_eq!(
+				select_services(&vector.s0, vector.subset_size, vector.network_size),
+				vector.services,
+				"s0 = {:?}, subset size {}, network size {}",
+				vector.s0,
+				vector.subset_size,
+				vector.network_size
+			);
+		}
+	}
+
+	#[test]
+	fn services_are_distinct_and_at_most_half_of_the_network() {
+		for network_size in 0..40 {
+			let services: Vec<usize> =
+				select_services(&H512::repeat_byte(network_size as u8), 20, network_size);
+			let mut distinct: Vec<usize> = services.clone();
+			distinct.sort();
+			distinct.dedup();
+
+			assert_eq!(services.len(), 20.min(network_size / 2));
+			assert_eq!(distinct.len(), services.len());
+			assert!(services.iter().all(|index| *index < network_size));
+		}
+	}
+
+	#[test]
+	fn services_do_not_depend_on_node_names() {
+		let key = |i: u8| sr25519::Public::from_raw([i; 32]);
+		let named: Vec<Node> = (0..10)
+			.map(|i| Node::new(format!("node {}", i), String::new(), key(i)))
+			.collect();
+		let anonymous: Vec<Node> =
+			(0..10).map(|i| Node::new(String::new(), String::new(), key(i))).collect();
+
+		let keys = |services: Vec<&Node>| {
+			services.iter().map(|node| *node.get_public_key()).collect::<Vec<_>>()
+		};
+		assert_eq!(
+			keys(create_services(&H512::repeat_byte(1), 3, &named)),
+			keys(create_services(&H512::repeat_byte(1), 3, &anonymous))
+		);
+	}
+
+	#[test]
+	fn uniform_below_rejects_the_incomplete_run() {
+		// The first u64 of the ChaCha20 stream keyed with zeros, 10393729187455219830,
+		// is rejected for a bound of 2^63 + 1
+		let mut rng: ChaCha20Rng = ChaCha20Rng::from_seed([0; 32]);
+		assert_eq!(uniform_below(&mut rng, (1 << 63) + 1), 2935650227004792128);
+
+		let mut rng: ChaCha20Rng = ChaCha20Rng::from_seed([0; 32]);
+		let draws: Vec<u64> = (0..8).map(|_| uniform_below(&mut rng, 10)).collect();
+		assert_eq!(draws, vec![0, 8, 7, 0, 2, 1, 0, 3]);
+	}
+
+	#[test]
+	fn proof_round_trips_through_scale() {
+		let seed: PoiSeed =
+			PoiSeed { attempt: 1, output: H256::repeat_byte(3), proof: H512::repeat_byte(3) };
+		let mut proof: PoiProof = PoiProof::new(H256::repeat_byte(1), H256::repeat_byte(2), seed);
+		proof
+			.push_hop(Hop {
+				signature: H512::repeat_byte(4),
+				counter_signature: H512::repeat_byte(5),
+			})
+			.unwrap();
+
+		let encoded: Vec<u8> = proof.encode();
+		// dependency, message, attempt, VRF output and proof, a compact length of 1, then one hop
+		assert_eq!(encoded.len(), 32 + 32 + 4 + 32 + 64 + 1 + 128);
+		assert_eq!(PoiProof::decode(&mut &encoded[..]).unwrap(), proof);
+	}
+
+	#[test]
+	fn proof_is_bounded() {
+		let hop: Hop =
+			Hop { signature: H512::repeat_byte(4), counter_signature: H512::repeat_byte(5) };
+		let seed: PoiSeed = PoiSeed { attempt: 0, output: H256::zero(), proof: H512::zero() };
+		let mut proof: PoiProof = PoiProof::new(H256::zero(), H256::zero(), seed);
+		for _ in 0..MAX_HOPS {
+			proof.push_hop(hop).unwrap();
+		}
+		assert_eq!(proof.push_hop(hop), Err(hop));
+
+		// A proof encoded with one hop too many does not decode
+		let mut hops: Vec<Hop> = proof.hops.to_vec();
+		hops.push(hop);
+		let mut encoded: Vec<u8> = (H256::zero(), H256::zero(), seed).encode();
+		encoded.extend(hops.encode());
+		assert!(PoiProof::decode(&mut &encoded[..]).is_err());
+	}
+
+	#[test]
+	fn seed_only_depends_on_the_key_chain_dependency_and_attempt() {
+		let pair: sr25519::Pair = sr25519::Pair::from_seed(&[1; 32]);
+		let (genesis_hash, dependency): (H256, H256) = (H256::repeat_byte(9), H256::repeat_byte(1));
+		let s0 = |seed: &PoiSeed| verify_seed(&pair.public(), seed, &genesis_hash, &dependency);
+
+		// The VRF proofs are randomized, the output is not
+		let (first, second): (PoiSeed, PoiSeed) = (
+			pair.vrf_seed(&genesis_hash, &dependency, 0).unwrap(),
+			pair.vrf_seed(&genesis_hash, &dependency, 0).unwrap(),
+		);
+		assert_eq!(first.output, second.output);
+		assert!(s0(&first).is_some());
+		assert_eq!(s0(&first), s0(&second));
+
+		let other_dependency: PoiSeed =
+			pair.vrf_seed(&genesis_hash, &H256::repeat_byte(2), 0).unwrap();
+		assert_ne!(other_dependency.output, first.output);
+		assert_eq!(s0(&other_dependency), None);
+		assert_eq!(verify_seed(&pair.public(), &first, &H256::zero(), &dependency), None);
+		assert_eq!(
+			verify_seed(
+				&sr25519::Pair::from_seed(&[2; 32]).public(),
+				&first,
+				&genesis_hash,
+				&dependency
+			),
+			None
+		);
+
+		let mut tampered: PoiSeed = first;
+		tampered.output.0[0] ^= 1;
+		assert_eq!(s0(&tampered), None);
+		let mut tampered: PoiSeed = first;
+		tampered.proof.0[0] ^= 1;
+		assert_eq!(s0(&tampered), None);
+		let mut tampered: PoiSeed = first;
+		tampered.attempt = 1;
+		assert_eq!(s0(&tampered), None);
+	}
+
+	#[test]
+	fn every_attempt_draws_its_own_seed() {
+		let pair: sr25519::Pair = sr25519::Pair::from_seed(&[1; 32]);
+		let (genesis_hash, dependency): (H256, H256) = (H256::repeat_byte(9), H256::repeat_byte(1));
+		let s0 = |seed: &PoiSeed| verify_seed(&pair.public(), seed, &genesis_hash, &dependency);
+
+		let seeds: Vec<H512> = (0..MAX_ATTEMPTS)
+			.map(|attempt| {
+				s0(&pair.vrf_seed(&genesis_hash, &dependency, attempt).unwrap()).unwrap()
+			})
+			.collect();
+		for (index, seed) in seeds.iter().enumerate() {
+			assert!(!seeds[..index].contains(seed));
+		}
+
+		// Attempts are bounded, so that u0 can only draw a few tours
+		let seed: PoiSeed = pair.vrf_seed(&genesis_hash, &dependency, MAX_ATTEMPTS).unwrap();
+		assert_eq!(s0(&seed), None);
+	}
+
+	#[cfg(feature = "async")]
+	const GENESIS_HASH: H256 = H256::repeat_byte(9);
+
+	#[cfg(feature = "async")]
+	fn test_network() -> (Vec<sr25519::Pair>, Vec<Node>) {
+		let pairs: Vec<sr25519::Pair> =
+			(1..=6).map(|i| sr25519::Pair::from_seed(&[i; 32])).collect();
+		let network: Vec<Node> = pairs
+			.iter()
+			.enumerate()
+			.map(|(i, pair)| Node::new(format!("node {}", i), String::new(), pair.public()))
+			.collect();
+		(pairs, network)
+	}
+
+	// The answer of the service `node` signing `challenge` with its key
+	#[cfg(feature = "async")]
+	fn answer(
+		pairs: &[sr25519::Pair],
+		node: &Node,
+		challenge: Challenge,
+	) -> Result<H512, PoiError> {
+		let pair: &sr25519::Pair =
+			pairs.iter().find(|pair| pair.public() == *node.get_public_key()).unwrap();
+		Ok(H512::from(pair.sign(&challenge.to_message()).0))
+	}
+
+	#[cfg(feature = "async")]
+	fn generate<F, Fut>(
+		pairs: &[sr25519::Pair],
+		network: &[Node],
+		config: &TourConfig,
+		ask_signature: F,
+	) -> Result<PoiProof, PoiError>
+	where
+		F: FnMut(&Node, Challenge) -> Fut,
+		Fut: Future<Output = Result<H512, PoiError>>,
+	{
+		generate_attempt(pairs, network, config, 0, ask_signature)
+	}
+
+	#[cfg(feature = "async")]
+	fn generate_attempt<F, Fut>(
+		pairs: &[sr25519::Pair],
+		network: &[Node],
+		config: &TourConfig,
+		attempt: u32,
+		ask_signature: F,
+	) -> Result<PoiProof, PoiError>
+	where
+		F: FnMut(&Node, Challenge) -> Fut,
+		Fut: Future<Output = Result<H512, PoiError>>,
+	{
+		let (dependency, message): (H256, H256) = (H256::repeat_byte(1), H256::repeat_byte(2));
+		futures::executor::block_on(generate_poi(
+			&pairs[0],
+			GENESIS_HASH,
+			dependency,
+			attempt,
+			message,
+			&PoiParameters::default(),
+			network,
+			config,
+			ask_signature,
+		))
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn generated_proof_is_valid() {
+		let (pairs, network) = test_network();
+
+		let proof: PoiProof =
+			generate(&pairs, &network, &TourConfig::default(), |node, challenge| {
+				futures::future::ready(answer(&pairs, node, challenge))
+			})
+			.unwrap();
+
+		assert!(proof.hops.len() >= network.len());
+		assert!(check_poi(
+			&proof,
+			&GENESIS_HASH,
+			&pairs[0].public(),
+			&PoiParameters::default(),
+			&network
+		));
+		assert!(!check_poi(
+			&proof,
+			&GENESIS_HASH,
+			&pairs[1].public(),
+			&PoiParameters::default(),
+			&network
+		));
+		assert!(!check_poi(
+			&proof,
+			&H256::zero(),
+			&pairs[0].public(),
+			&PoiParameters::default(),
+			&network
+		));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn later_attempts_are_valid_up_to_the_limit() {
+		let (pairs, network) = test_network();
+		let sign = |node: &Node, challenge| futures::future::ready(answer(&pairs, node, challenge));
+
+		let proof: PoiProof =
+			generate_attempt(&pairs, &network, &TourConfig::default(), MAX_ATTEMPTS - 1, sign)
+				.unwrap();
+		assert_eq!(proof.seed.attempt, MAX_ATTEMPTS - 1);
+		assert!(check_poi(
+			&proof,
+			&GENESIS_HASH,
+			&pairs[0].public(),
+			&PoiParameters::default(),
+			&network
+		));
+
+		assert_eq!(
+			generate_attempt(&pairs, &network, &TourConfig::default(), MAX_ATTEMPTS, sign),
+			Err(PoiError::NoAttemptLeft)
+		);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn transient_failures_are_retried() {
+		let (pairs, network) = test_network();
+		let config: TourConfig = TourConfig { max_retries: 1, ..Default::default() };
+
+		// Every service fails to answer the first time it is asked
+		let mut calls: u32 = 0;
+		let proof: PoiProof = generate(&pairs, &network, &config, |node, challenge| {
+			calls += 1;
+			futures::future::ready(if calls % 2 == 1 {
+				Err(PoiError::PeerUnreachable)
+			} else {
+				answer(&pairs, node, challenge)
+			})
+		})
+		.unwrap();
+
+		assert_eq!(calls as usize, 2 * proof.hops.len());
+		assert!(check_poi(
+			&proof,
+			&GENESIS_HASH,
+			&pairs[0].public(),
+			&PoiParameters::default(),
+			&network
+		));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn gives_up_after_max_retries() {
+		let (pairs, network) = test_network();
+		let config: TourConfig = TourConfig { max_retries: 2, ..Default::default() };
+
+		let mut calls: u32 = 0;
+		let result = generate(&pairs, &network, &config, |_, _| {
+			calls += 1;
+			futures::future::ready(Err(PoiError::MalformedResponse))
+		});
+
+		assert_eq!(result, Err(PoiError::MalformedResponse));
+		assert_eq!(calls, 3);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn permanent_failures_are_not_retried() {
+		let (pairs, network) = test_network();
+
+		let mut calls: u32 = 0;
+		let result = generate(&pairs, &network, &TourConfig::default(), |_, _| {
+			calls += 1;
+			futures::future::ready(Err(PoiError::ParseFailure))
+		});
+
+		assert_eq!(result, Err(PoiError::ParseFailure));
+		assert_eq!(calls, 1);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn slow_services_time_out() {
+		let (pairs, network) = test_network();
+		let config: TourConfig =
+			TourConfig { hop_timeout: Duration::from_millis(10), max_retries: 1 };
+
+		let result = generate(&pairs, &network, &config, |_, _| futures::future::pending());
+
+		assert_eq!(result, Err(PoiError::PeerUnreachable));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn invalid_signatures_are_rejected() {
+		let (pairs, network) = test_network();
+		let stranger: sr25519::Pair = sr25519::Pair::from_seed(&[99; 32]);
+
+		let result = generate(&pairs, &network, &TourConfig::default(), |_, challenge| {
+			futures::future::ready(Ok(H512::from(stranger.sign(&challenge.to_message()).0)))
+		});
+
+		assert_eq!(result, Err(PoiError::BadSignature));
+	}
+}
@@ -0,0 +1,157 @@
+//! Asks a set of dev nodes for the signatures of a tour and prints the proof of interaction.
+
+use std::str::FromStr;
+
+use clap::Parser;
+use serde_json::{json, Value};
+
+use sp_core::{sr25519, Pair, H256, H512};
+
+use poi_core::{
+	check_poi, generate_poi, Challenge, Node, PoiError, PoiId, PoiParameters, PoiProof, TourConfig,
+};
+
+/// A dev node, whose PoI key is derived from the `//NAME` seed as set by the dev chain spec, and
+/// the URL of its HTTP RPC.
+#[derive(Clone, Debug)]
+struct DevNode {
+	name: String,
+	url: String,
+}
+
+impl FromStr for DevNode {
+	type Err = String;
+
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		let (name, url) = value
+			.split_once('=')
+			.ok_or_else(|| format!("expected NAME=URL, got {}", value))?;
+		Ok(DevNode { name: name.to_string(), url: url.to_string() })
+	}
+}
+
+#[derive(Debug, Parser)]
+#[clap(about = "Generates a proof of interaction with the signatures of dev nodes")]
+struct Cli {
+	/// A node of the network set N, as `NAME=URL`, e.g. `Alice=http://127.0.0.1:9933/`.
+	#[clap(long = "node", required = true)]
+	nodes: Vec<DevNode>,
+	/// Name of the dev key the proof is generated with.
+	#[clap(long, default_value = "Alice")]
+	initiator: String,
+}
+
+/// Sends the JSON-RPC `payload` to `url` and returns the string it results in.
+async fn send(url: &str, payload: &Value) -> Result<String, PoiError> {
+	let response = reqwest::Client::new()
+		.post(url)
+		.header("Content-Type", "application/json")
+		.body(payload.to_string())
+		.send()
+		.await
+		.map_err(|_| PoiError::PeerUnreachable)?;
+	let body = response.text().await.map_err(|_| PoiError::PeerUnreachable)?;
+	let parsed: Value = serde_json::from_str(&body).map_err(|_| PoiError::MalformedResponse)?;
+	parsed["result"].as_str().map(str::to_string).ok_or(PoiError::MalformedResponse)
+}
+
+/// Asks the service node reached at `url` to sign `challenge` on behalf of `initiator` through
+/// its `poi_signChallenge` RPC.
+async fn ask_signature(
+	url: String,
+	initiator: PoiId,
+	challenge: Challenge,
+) -> Result<H512, PoiError> {
+	let payload = json!({
+		"id": 1,
+		"jsonrpc": "2.0",
+		"method": "poi_signChallenge",
+		"params": [initiator, challenge]
+	});
+
+	println!("Asking {} to sign hop {}.", url, challenge.hop);
+	let signature = send(&url, &payload).await.map_err(|err| {
+		eprintln!("{} failed to sign: {}", url, err);
+		err
+	})?;
+	H512::from_str(signature.trim_start_matches("0x")).map_err(|_| PoiError::ParseFailure)
+}
+
+/// Asks the node reached at `url` for the hash of the block `number`, or of its best block.
+async fn block_hash(url: &str, number: Option<u32>) -> Result<H256, PoiError> {
+	let payload = json!({
+		"id": 1,
+		"jsonrpc": "2.0",
+		"method": "chain_getBlockHash",
+		"params": number.map(|number| vec![number]).unwrap_or_default()
+	});
+
+	let hash: String = send(url, &payload).await?;
+	H256::from_str(hash.trim_start_matches("0x")).map_err(|_| PoiError::ParseFailure)
+}
+
+/// The key pair of a dev node, as set by the dev chain spec.
+fn dev_pair(name: &str) -> sr25519::Pair {
+	sr25519::Pair::from_string(&format!("//{}", name), None).expect("dev seeds are valid; qed")
+}
+
+#[tokio::main]
+async fn main() {
+	let cli = Cli::parse();
+
+	// N is sorted by key, as the nodes build it from the on-chain authorities
+	let mut network: Vec<Node> = cli
+		.nodes
+		.into_iter()
+		.map(|node| Node::new(node.name.clone(), node.url, dev_pair(&node.name).public()))
+		.collect();
+	network.sort_by_key(|node| *node.get_public_key());
+	for node in network.iter() {
+		println!("{} at {}, key {}", node.get_name(), node.get_ip(), node.get_public_key());
+	}
+
+	// The challenges are bound to the chain and to its best block
+	let url: &str = network[0].get_ip();
+	let (genesis_hash, best_hash): (H256, H256) =
+		match (block_hash(url, Some(0)).await, block_hash(url, None).await) {
+			(Ok(genesis_hash), Ok(best_hash)) => (genesis_hash, best_hash),
+			_ => {
+				eprintln!("Failed to get the genesis and best blocks from {}", url);
+				return
+			},
+		};
+	// The demo seals no actual block, so it stands for the pre-hash of a block built on the best
+	// one
+	let pre_hash: H256 = H256::from_low_u64_be(999);
+	let parameters: PoiParameters = PoiParameters::default();
+	let config: TourConfig = TourConfig::default();
+	let u0: sr25519::Pair = dev_pair(&cli.initiator);
+	let initiator: PoiId = PoiId::from(u0.public());
+	let proof: PoiProof = match generate_poi(
+		&u0,
+		genesis_hash,
+		best_hash,
+		0,
+		pre_hash,
+		&parameters,
+		&network,
+		&config,
+		|node, challenge| ask_signature(node.get_ip().to_string(), initiator.clone(), challenge),
+	)
+	.await
+	{
+		Ok(proof) => proof,
+		Err(err) => {
+			eprintln!("Failed to generate the PoI: {}", err);
+			return
+		},
+	};
+
+	println!("{} signatures required to seal a block on {:?}.", proof.hops.len(), best_hash);
+	println!("s0: {:?}", proof.seed.output);
+	for (index, hop) in proof.hops.iter().enumerate() {
+		println!("s{}: {:?}", index + 1, hop.signature);
+		println!("s{}': {:?}", index + 1, hop.counter_signature);
+	}
+	println!("Valid: {}", check_poi(&proof, &genesis_hash, &u0.public(), &parameters, &network));
+}
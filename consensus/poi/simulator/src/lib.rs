@@ -0,0 +1,219 @@
+//! Simulates Proof-of-Interaction on a network of virtual nodes.
+//!
+//! Every node holds a real sr25519 key pair, and tours are generated and checked with
+//! `poi-core` exactly as the node does, but signing requests go through a [`Transport`], which
+//! the [`SimulatedTransport`] carries in process over links of configurable latency and loss.
+//! The outcome is a [`Report`] of the block times, proof sizes and signing load of the nodes.
+
+use futures::FutureExt;
+use parity_scale_codec::Encode;
+use poi_core::{
+	check_poi, generate_poi, hash, Challenge, Node, PoiParameters, TourConfig, MAX_ATTEMPTS,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sp_core::{sr25519, H256};
+use std::{cell::RefCell, collections::HashMap};
+
+pub mod report;
+pub mod transport;
+
+pub use report::{BlockRecord, NodeLoad, Report};
+pub use transport::{LinkConfig, SimulatedTransport, Transport, VirtualNode};
+
+/// What to simulate.
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+	/// Blocks to seal.
+	pub blocks: u32,
+	/// Parameters the tours are drawn with.
+	pub parameters: PoiParameters,
+	/// Timeouts and retries of the hops.
+	pub tour: TourConfig,
+	/// Tours started for a block before giving up on it, at most [`MAX_ATTEMPTS`].
+	pub max_attempts: u32,
+	/// Seed of the draw of the initiators.
+	pub seed: u64,
+}
+
+/// Seal `config.blocks` blocks on top of each other, each one by a node drawn at random among
+/// `nodes`, reaching the services through `transport`.
+///
+/// A failed tour is followed by the next attempt of the initiator, which draws another `s0` and so
+/// other services and another length, until `config.max_attempts` is reached, in which case the
+/// block is recorded as invalid and the chain goes on from it anyway.
+pub fn simulate<T: Transport>(
+	transport: &T,
+	nodes: &[VirtualNode],
+	config: &SimulationConfig,
+) -> Report {
+	// Nodes build the network from the authorities sorted by key, tours must be drawn from it
+	let mut network: Vec<Node> = nodes.iter().map(VirtualNode::to_node).collect();
+	network.sort_by_key(|node| *node.get_public_key());
+
+	let loads: RefCell<HashMap<sr25519::Public, NodeLoad>> = RefCell::new(
+		nodes
+			.iter()
+			.map(|node| (node.public(), NodeLoad { name: node.name.clone(), ..Default::default() }))
+			.collect(),
+	);
+	let ask_signature = |service: &Node, challenge: Challenge| {
+		let key = *service.get_public_key();
+		if let Some(load) = loads.borrow_mut().get_mut(&key) {
+			load.requests += 1;
+		}
+		transport.request(service, challenge).inspect(move |answer| {
+			if let (Ok(_), Some(load)) = (answer, loads.borrow_mut().get_mut(&key)) {
+				load.signatures += 1;
+			}
+		})
+	};
+
+	let mut rng = StdRng::seed_from_u64(config.seed);
+	let genesis_hash = hash(b"poi-simulator");
+	let mut parent = genesis_hash;
+	let max_attempts = config.max_attempts.min(MAX_ATTEMPTS);
+	let mut blocks = Vec::with_capacity(config.blocks as usize);
+	for number in 1..=config.blocks {
+		let initiator = &nodes[rng.gen_range(0..nodes.len())];
+		let message: H256 = hash(&(number, parent).encode());
+		let start = transport.elapsed();
+
+		let mut record = BlockRecord {
+			number,
+			initiator: initiator.name.clone(),
+			attempts: 0,
+			block_time_ms: 0,
+			hops: 0,
+			proof_size: 0,
+			valid: false,
+		};
+		while record.attempts < max_attempts && !record.valid {
+			let proof = futures::executor::block_on(generate_poi(
+				&initiator.pair,
+				genesis_hash,
+				parent,
+				record.attempts,
+				message,
+				&config.parameters,
+				&network,
+				&config.tour,
+				ask_signature,
+			));
+			record.attempts += 1;
+			if let Ok(proof) = proof {
+				record.valid = check_poi(
+					&proof,
+					&genesis_hash,
+					&initiator.public(),
+					&config.parameters,
+					&network,
+				);
+				record.hops = proof.hops.len() as u32;
+				record.proof_size = proof.encoded_size() as u32;
+			}
+		}
+		record.block_time_ms = (transport.elapsed() - start).as_millis() as u64;
+
+		blocks.push(record);
+		parent = message;
+	}
+
+	let loads = loads.into_inner();
+	let nodes = nodes.iter().filter_map(|node| loads.get(&node.public()).cloned()).collect();
+	Report::new(blocks, nodes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use poi_core::{FixedPointNumber, FixedU64};
+	use std::time::Duration;
+
+	fn link(drop_rate: f64) -> LinkConfig {
+		LinkConfig {
+			latency: Duration::from_millis(50),
+			jitter: Duration::from_millis(20),
+			drop_rate,
+			timeout: Duration::from_secs(5),
+		}
+	}
+
+	fn config() -> SimulationConfig {
+		SimulationConfig {
+			blocks: 5,
+			parameters: PoiParameters {
+				difficulty: FixedU64::saturating_from_integer(10),
+				..Default::default()
+			},
+			tour: TourConfig::default(),
+			max_attempts: 3,
+			seed: 0,
+		}
+	}
+
+	#[test]
+	fn seals_every_block_on_a_reliable_network() {
+		let nodes = VirtualNode::generate(8, 0);
+		let report = simulate(&SimulatedTransport::new(&nodes, link(0.0), 0), &nodes, &config());
+
+		assert_eq!(report.summary.valid_blocks, 5);
+		assert_eq!(report.summary.failed_attempts, 0);
+		assert!(report.blocks.iter().all(|block| block.hops > 0 && block.proof_size > 0));
+		// Without losses, every hop takes between the latency and the latency plus the jitter
+		for block in &report.blocks {
+			assert!(block.block_time_ms >= 50 * block.hops as u64);
+			assert!(block.block_time_ms <= 70 * block.hops as u64);
+		}
+
+		let hops: u64 = report.blocks.iter().map(|block| block.hops as u64).sum();
+		let signatures: u64 = report.nodes.iter().map(|node| node.signatures).sum();
+		assert_eq!(report.nodes.len(), 8);
+		assert_eq!(signatures, hops);
+	}
+
+	#[test]
+	fn is_deterministic() {
+		let nodes = VirtualNode::generate(8, 1);
+		let run = || simulate(&SimulatedTransport::new(&nodes, link(0.05), 7), &nodes, &config());
+
+		assert_eq!(run().blocks_csv(), run().blocks_csv());
+	}
+
+	#[test]
+	fn gets_past_an_offline_service() {
+		let nodes = VirtualNode::generate(8, 0);
+		let config = SimulationConfig {
+			blocks: 1,
+			parameters: PoiParameters { subset_size: 1, ..config().parameters },
+			max_attempts: MAX_ATTEMPTS,
+			..config()
+		};
+
+		// The single service the first attempt draws signs every hop of its tour
+		let report = simulate(&SimulatedTransport::new(&nodes, link(0.0), 0), &nodes, &config);
+		assert_eq!(report.blocks[0].attempts, 1);
+		let service = report.nodes.iter().position(|node| node.signatures > 0).unwrap();
+
+		// Once it is offline, the next attempts draw other services
+		let transport =
+			SimulatedTransport::new(&nodes, link(0.0), 0).with_offline(&[nodes[service].public()]);
+		let report = simulate(&transport, &nodes, &config);
+
+		assert_eq!(report.summary.valid_blocks, 1);
+		assert!(report.blocks[0].attempts > 1);
+		assert_eq!(report.nodes[service].signatures, 0);
+	}
+
+	#[test]
+	fn gives_up_when_every_request_is_lost() {
+		let nodes = VirtualNode::generate(8, 0);
+		let report = simulate(&SimulatedTransport::new(&nodes, link(1.0), 0), &nodes, &config());
+
+		assert_eq!(report.summary.valid_blocks, 0);
+		assert_eq!(report.summary.failed_attempts, 15);
+		assert!(report.nodes.iter().all(|node| node.signatures == 0));
+		// Every attempt gave up after the first hop timed out on each try
+		let timeouts = u64::from(config().max_attempts * (TourConfig::default().max_retries + 1));
+		assert!(report.blocks.iter().all(|block| block.block_time_ms == timeouts * 5000));
+	}
+}
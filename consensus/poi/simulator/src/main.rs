@@ -0,0 +1,158 @@
+//! Runs a PoI simulation and writes its report.
+
+use clap::{Parser, ValueEnum};
+use poi_core::{FixedU64, PoiParameters, TourConfig, TourLengthDistribution, MAX_ATTEMPTS};
+use poi_simulator::{simulate, LinkConfig, SimulatedTransport, SimulationConfig, VirtualNode};
+use std::{fs, io, path::PathBuf, time::Duration};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+	/// `summary.csv`, `blocks.csv` and `nodes.csv`.
+	Csv,
+	/// `report.json`.
+	Json,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Distribution {
+	Normal,
+	Geometric,
+	Uniform,
+}
+
+impl From<Distribution> for TourLengthDistribution {
+	fn from(distribution: Distribution) -> Self {
+		match distribution {
+			Distribution::Normal => TourLengthDistribution::Normal,
+			Distribution::Geometric => TourLengthDistribution::Geometric,
+			Distribution::Uniform => TourLengthDistribution::Uniform,
+		}
+	}
+}
+
+#[derive(Debug, Parser)]
+#[clap(about = "Simulates Proof-of-Interaction on a network of virtual nodes")]
+struct Cli {
+	/// Nodes of the network.
+	#[clap(long, default_value_t = 20)]
+	nodes: usize,
+	/// Blocks to seal.
+	#[clap(long, default_value_t = 100)]
+	blocks: u32,
+	/// Maximum number of services a tour is drawn from.
+	#[clap(long, default_value_t = 20)]
+	subset_size: u32,
+	/// Standard deviation of the tour length, per node of the network.
+	#[clap(long, default_value_t = 0.1)]
+	std_deviation: f64,
+	/// Minimum tour length, per node of the network.
+	#[clap(long, default_value_t = 1.0)]
+	min_tour_length: f64,
+	/// Mean tour length.
+	#[clap(long, default_value_t = 20.0)]
+	difficulty: f64,
+	/// Distribution the tour length is drawn from.
+	#[clap(long, value_enum, default_value_t = Distribution::Normal)]
+	distribution: Distribution,
+	/// Round-trip time of a signing request, in milliseconds.
+	#[clap(long, default_value_t = 50)]
+	latency_ms: u64,
+	/// Up to this many milliseconds are randomly added to the latency of each request.
+	#[clap(long, default_value_t = 20)]
+	jitter_ms: u64,
+	/// Probability for a signing request to be lost.
+	#[clap(long, default_value_t = 0.01)]
+	drop_rate: f64,
+	/// Time a service is given to answer, in milliseconds.
+	#[clap(long, default_value_t = 5000)]
+	hop_timeout_ms: u64,
+	/// Times a service failing to answer is asked again.
+	#[clap(long, default_value_t = 2)]
+	max_retries: u32,
+	/// Tours started for a block before giving up on it, each drawing other services.
+	#[clap(long, default_value_t = 3)]
+	max_attempts: u32,
+	/// Nodes that never answer, the first ones generated.
+	#[clap(long, default_value_t = 0)]
+	offline: usize,
+	/// Seed of the keys, the links and the draw of the initiators.
+	#[clap(long, default_value_t = 0)]
+	seed: u64,
+	#[clap(long, value_enum, default_value_t = Format::Csv)]
+	format: Format,
+	/// Directory the report is written to.
+	#[clap(long, default_value = ".")]
+	output: PathBuf,
+}
+
+/// `value` in the fixed-point format of the on-chain parameters, negative values being zero.
+fn fixed(value: f64) -> FixedU64 {
+	FixedU64::from_inner((value.max(0.0) * 1_000_000_000.0).round() as u64)
+}
+
+fn main() -> io::Result<()> {
+	let cli = Cli::parse();
+	if cli.nodes < 2 {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "at least two nodes are needed"))
+	}
+
+	let parameters = PoiParameters {
+		subset_size: cli.subset_size,
+		std_deviation_coefficient: fixed(cli.std_deviation),
+		min_tour_length: fixed(cli.min_tour_length),
+		difficulty: fixed(cli.difficulty),
+		distribution: cli.distribution.into(),
+	};
+	if cli.max_attempts > MAX_ATTEMPTS {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("an initiator makes at most {} attempts on a block", MAX_ATTEMPTS),
+		))
+	}
+	if !parameters.is_valid_for(cli.nodes.try_into().unwrap_or(u32::MAX)) {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"PoI parameters are out of the bounds the chain accepts",
+		))
+	}
+
+	let hop_timeout = Duration::from_millis(cli.hop_timeout_ms);
+	let nodes = VirtualNode::generate(cli.nodes, cli.seed);
+	let link = LinkConfig {
+		latency: Duration::from_millis(cli.latency_ms),
+		jitter: Duration::from_millis(cli.jitter_ms),
+		drop_rate: cli.drop_rate,
+		timeout: hop_timeout,
+	};
+	let config = SimulationConfig {
+		blocks: cli.blocks,
+		parameters,
+		tour: TourConfig { hop_timeout, max_retries: cli.max_retries },
+		max_attempts: cli.max_attempts,
+		seed: cli.seed,
+	};
+
+	let offline: Vec<_> = nodes.iter().take(cli.offline).map(VirtualNode::public).collect();
+	let transport = SimulatedTransport::new(&nodes, link, cli.seed).with_offline(&offline);
+	let report = simulate(&transport, &nodes, &config);
+
+	fs::create_dir_all(&cli.output)?;
+	match cli.format {
+		Format::Csv => {
+			fs::write(cli.output.join("summary.csv"), report.summary_csv())?;
+			fs::write(cli.output.join("blocks.csv"), report.blocks_csv())?;
+			fs::write(cli.output.join("nodes.csv"), report.nodes_csv())?;
+		},
+		Format::Json => fs::write(cli.output.join("report.json"), report.to_json()?)?,
+	}
+
+	let summary = &report.summary;
+	println!(
+		"{} blocks sealed, {} failed, mean block time {:.0} ms, mean proof size {:.0} bytes",
+		summary.valid_blocks,
+		summary.failed_blocks,
+		summary.block_time_ms.mean,
+		summary.proof_size.mean
+	);
+	Ok(())
+}
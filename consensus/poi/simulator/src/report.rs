@@ -0,0 +1,219 @@
+//! What a simulation measured, as JSON or CSV.
+
+use serde::Serialize;
+use std::fmt::Write;
+
+/// A block of the simulated chain.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRecord {
+	pub number: u32,
+	/// Name of the node that sealed the block.
+	pub initiator: String,
+	/// Tours started before one succeeded, the successful one included.
+	pub attempts: u32,
+	/// Time spent sealing the block, over all attempts.
+	pub block_time_ms: u64,
+	/// Hops of the proof sealing the block.
+	pub hops: u32,
+	/// Size of the SCALE-encoded proof, in bytes.
+	pub proof_size: u32,
+	/// Whether the block was sealed with a proof passing `check_poi`.
+	pub valid: bool,
+}
+
+/// The signing requests a node received.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeLoad {
+	pub name: String,
+	/// Requests sent to the node.
+	pub requests: u64,
+	/// Requests the node answered with a valid signature.
+	pub signatures: u64,
+}
+
+/// Distribution of a measure over the blocks of a simulation.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Distribution {
+	pub min: u64,
+	pub mean: f64,
+	pub median: u64,
+	pub p90: u64,
+	pub p99: u64,
+	pub max: u64,
+}
+
+impl Distribution {
+	/// The distribution of `values`, all zeros when there is none.
+	pub fn of(values: impl IntoIterator<Item = u64>) -> Self {
+		let mut values: Vec<u64> = values.into_iter().collect();
+		if values.is_empty() {
+			return Self::default()
+		}
+		values.sort_unstable();
+
+		let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+		Self {
+			min: values[0],
+			mean: values.iter().sum::<u64>() as f64 / values.len() as f64,
+			median: percentile(50),
+			p90: percentile(90),
+			p99: percentile(99),
+			max: values[values.len() - 1],
+		}
+	}
+}
+
+/// Overview of a simulation.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+	/// Blocks sealed with a valid proof.
+	pub valid_blocks: u32,
+	/// Blocks that could not be sealed.
+	pub failed_blocks: u32,
+	/// Tours that failed, including those of blocks sealed at a later attempt.
+	pub failed_attempts: u32,
+	/// Block times of the valid blocks, in milliseconds.
+	pub block_time_ms: Distribution,
+	/// Proof sizes of the valid blocks, in bytes.
+	pub proof_size: Distribution,
+}
+
+/// Everything a simulation measured.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+	pub summary: Summary,
+	pub blocks: Vec<BlockRecord>,
+	pub nodes: Vec<NodeLoad>,
+}
+
+impl Report {
+	/// A report of `blocks` and `nodes`, summarized.
+	pub fn new(blocks: Vec<BlockRecord>, nodes: Vec<NodeLoad>) -> Self {
+		let valid = || blocks.iter().filter(|block| block.valid);
+		let summary = Summary {
+			valid_blocks: valid().count() as u32,
+			failed_blocks: blocks.iter().filter(|block| !block.valid).count() as u32,
+			failed_attempts: blocks
+				.iter()
+				.map(|block| block.attempts - u32::from(block.valid))
+				.sum(),
+			block_time_ms: Distribution::of(valid().map(|block| block.block_time_ms)),
+			proof_size: Distribution::of(valid().map(|block| u64::from(block.proof_size))),
+		};
+
+		Self { summary, blocks, nodes }
+	}
+
+	/// The whole report as a JSON document.
+	pub fn to_json(&self) -> serde_json::Result<String> {
+		serde_json::to_string_pretty(self)
+	}
+
+	/// One line per block.
+	pub fn blocks_csv(&self) -> String {
+		let mut csv =
+			String::from("number,initiator,attempts,block_time_ms,hops,proof_size,valid\n");
+		for block in &self.blocks {
+			let _ = writeln!(
+				csv,
+				"{},{},{},{},{},{},{}",
+				block.number,
+				block.initiator,
+				block.attempts,
+				block.block_time_ms,
+				block.hops,
+				block.proof_size,
+				block.valid
+			);
+		}
+		csv
+	}
+
+	/// One line per node.
+	pub fn nodes_csv(&self) -> String {
+		let mut csv = String::from("name,requests,signatures\n");
+		for node in &self.nodes {
+			let _ = writeln!(csv, "{},{},{}", node.name, node.requests, node.signatures);
+		}
+		csv
+	}
+
+	/// The summary, one line per measure.
+	pub fn summary_csv(&self) -> String {
+		let mut csv = String::from("measure,min,mean,median,p90,p99,max\n");
+		for (measure, distribution) in [
+			("block_time_ms", &self.summary.block_time_ms),
+			("proof_size", &self.summary.proof_size),
+		] {
+			let _ = writeln!(
+				csv,
+				"{},{},{:.2},{},{},{},{}",
+				measure,
+				distribution.min,
+				distribution.mean,
+				distribution.median,
+				distribution.p90,
+				distribution.p99,
+				distribution.max
+			);
+		}
+		csv
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn block(number: u32, block_time_ms: u64, valid: bool) -> BlockRecord {
+		BlockRecord {
+			number,
+			initiator: "node-0".into(),
+			attempts: 2,
+			block_time_ms,
+			hops: 20,
+			proof_size: 2689,
+			valid,
+		}
+	}
+
+	#[test]
+	fn distribution_of_values() {
+		let distribution = Distribution::of((1..=100).rev());
+
+		assert_eq!(distribution.min, 1);
+		assert_eq!(distribution.mean, 50.5);
+		assert_eq!(distribution.median, 50);
+		assert_eq!(distribution.p90, 90);
+		assert_eq!(distribution.p99, 99);
+		assert_eq!(distribution.max, 100);
+		assert_eq!(Distribution::of(Vec::new()), Distribution::default());
+	}
+
+	#[test]
+	fn summary_only_accounts_valid_blocks() {
+		let report = Report::new(vec![block(1, 100, true), block(2, 900, false)], Vec::new());
+
+		assert_eq!(report.summary.valid_blocks, 1);
+		assert_eq!(report.summary.failed_blocks, 1);
+		assert_eq!(report.summary.failed_attempts, 3);
+		assert_eq!(report.summary.block_time_ms.max, 100);
+	}
+
+	#[test]
+	fn csv_has_a_header_and_a_line_per_record() {
+		let nodes = vec![NodeLoad { name: "node-0".into(), requests: 3, signatures: 2 }];
+		let report = Report::new(vec![block(1, 100, true)], nodes);
+
+		assert_eq!(
+			report.blocks_csv(),
+			"number,initiator,attempts,block_time_ms,hops,proof_size,valid\n\
+			1,node-0,2,100,20,2689,true\n"
+		);
+		assert_eq!(report.nodes_csv(), "name,requests,signatures\nnode-0,3,2\n");
+		assert_eq!(report.summary_csv().lines().count(), 3);
+	}
+}
@@ -0,0 +1,136 @@
+//! Carrying the signing requests of tours to the services.
+
+use futures::{
+	future::{self, BoxFuture},
+	FutureExt,
+};
+use parity_scale_codec::Encode;
+use poi_core::{Challenge, Node, PoiError};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sp_core::{hashing::blake2_256, sr25519, Pair, H512};
+use std::{
+	cell::{Cell, RefCell},
+	collections::{HashMap, HashSet},
+	time::Duration,
+};
+
+/// A node of the simulated network, holding a real sr25519 key pair.
+pub struct VirtualNode {
+	pub name: String,
+	pub pair: sr25519::Pair,
+}
+
+impl VirtualNode {
+	/// `count` nodes whose keys are derived from `seed`.
+	pub fn generate(count: usize, seed: u64) -> Vec<VirtualNode> {
+		(0..count)
+			.map(|index| VirtualNode {
+				name: format!("node-{}", index),
+				pair: sr25519::Pair::from_seed(&blake2_256(&(seed, index as u64).encode())),
+			})
+			.collect()
+	}
+
+	pub fn public(&self) -> sr25519::Public {
+		self.pair.public()
+	}
+
+	/// The node as tours see it.
+	pub fn to_node(&self) -> Node {
+		Node::new(self.name.clone(), String::new(), self.public())
+	}
+}
+
+/// Carries the signing requests of tours to the services.
+pub trait Transport {
+	/// Ask `service` to sign `challenge`.
+	fn request(
+		&self,
+		service: &Node,
+		challenge: Challenge,
+	) -> BoxFuture<'static, Result<H512, PoiError>>;
+
+	/// Time spent carrying requests so far, be it simulated or real.
+	fn elapsed(&self) -> Duration;
+}
+
+/// How the links between simulated nodes behave.
+#[derive(Clone, Debug)]
+pub struct LinkConfig {
+	/// Round-trip time of a request.
+	pub latency: Duration,
+	/// Up to this much time is randomly added to the latency of each request.
+	pub jitter: Duration,
+	/// Probability for a request, or its answer, to be lost.
+	pub drop_rate: f64,
+	/// Time after which an initiator gives up on a request, which should match the hop timeout
+	/// of the tours.
+	pub timeout: Duration,
+}
+
+/// Answers requests in process, on a virtual clock: requests complete immediately but their
+/// latency, or the timeout when they are lost, is added to the clock.
+pub struct SimulatedTransport {
+	pairs: Vec<sr25519::Pair>,
+	index: HashMap<sr25519::Public, usize>,
+	offline: HashSet<sr25519::Public>,
+	link: LinkConfig,
+	rng: RefCell<StdRng>,
+	elapsed: Cell<Duration>,
+}
+
+impl SimulatedTransport {
+	/// A transport reaching `nodes`, drawing latencies and losses from `seed`.
+	pub fn new(nodes: &[VirtualNode], link: LinkConfig, seed: u64) -> Self {
+		Self {
+			pairs: nodes.iter().map(|node| node.pair.clone()).collect(),
+			index: nodes.iter().enumerate().map(|(index, node)| (node.public(), index)).collect(),
+			offline: HashSet::new(),
+			link,
+			rng: RefCell::new(StdRng::seed_from_u64(seed)),
+			elapsed: Cell::new(Duration::ZERO),
+		}
+	}
+
+	/// The transport, the nodes of keys `offline` never answering, as if they were down.
+	pub fn with_offline(mut self, offline: &[sr25519::Public]) -> Self {
+		self.offline.extend(offline.iter().copied());
+		self
+	}
+
+	fn advance(&self, duration: Duration) {
+		self.elapsed.set(self.elapsed.get() + duration);
+	}
+}
+
+impl Transport for SimulatedTransport {
+	fn request(
+		&self,
+		service: &Node,
+		challenge: Challenge,
+	) -> BoxFuture<'static, Result<H512, PoiError>> {
+		let pair = match self.index.get(service.get_public_key()) {
+			Some(index) => &self.pairs[*index],
+			None => return future::ready(Err(PoiError::PeerUnreachable)).boxed(),
+		};
+
+		let (latency, dropped) = {
+			let mut rng = self.rng.borrow_mut();
+			let latency = self.link.latency + self.link.jitter.mul_f64(rng.gen::<f64>());
+			(latency, rng.gen_bool(self.link.drop_rate.clamp(0.0, 1.0)))
+		};
+		if dropped || latency > self.link.timeout || self.offline.contains(service.get_public_key())
+		{
+			self.advance(self.link.timeout);
+			return future::ready(Err(PoiError::PeerUnreachable)).boxed()
+		}
+
+		self.advance(latency);
+		let signature = H512::from(pair.sign(&challenge.to_message()).0);
+		future::ready(Ok(signature)).boxed()
+	}
+
+	fn elapsed(&self) -> Duration {
+		self.elapsed.get()
+	}
+}
@@ -1,120 +1,115 @@
-use jsonrpc::simple_http::{self, SimpleHttpTransport};
-use jsonrpc::Client;
+use pallet_poi_parameters_runtime_api::PoiParametersApi;
+use pallet_poi_runtime_api::PoiApi;
 use parity_scale_codec::{Decode, Encode};
+use poi_core::{check_poi, FixedPointNumber, PoiProof};
 use sc_consensus_pow::{Error, PowAlgorithm};
-use sha3::{Digest, Sha3_256};
-use sp_api::ProvideRuntimeApi;
-use sp_consensus_pow::{DifficultyApi, Seal as RawSeal};
-use sp_authority_discovery::{AuthorityDiscoveryApi, AuthorityId};
+use sp_api::{ApiError, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_consensus_pow::Seal as RawSeal;
 use sp_core::{H256, U256};
-use sp_runtime::generic::BlockId;
-use sp_runtime::traits::Block as BlockT;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
 use std::sync::Arc;
 
-/// Determine whether the given hash satisfies the given difficulty.
-/// The test is done by multiplying the two together. If the product
-/// overflows the bounds of U256, then the product (and thus the hash)
-/// was too high.
-pub fn hash_meets_difficulty(hash: &H256, difficulty: U256) -> bool {
-	let num_hash = U256::from(&hash[..]);
-	let (_, overflowed) = num_hash.overflowing_mul(difficulty);
+pub mod network;
+pub mod protocol;
+pub mod prover;
+mod rate_limit;
+pub mod rpc;
 
-	!overflowed
-}
+pub use network::network_nodes;
+pub use poi_core::{PoiId, PoiParameters};
 
 /// A Seal struct that will be encoded to a Vec<u8> as used as the
-/// `RawSeal` type.
+/// `RawSeal` type. It carries the whole proof of interaction.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
 pub struct Seal {
-	pub difficulty: U256,
-	pub signature: H256,
-	pub peer: U256,
+	/// PoI key of the authority that initiated the tour and signed the proof.
+	pub initiator: PoiId,
+	/// The proof of interaction, along with the dependency and message it was computed over.
+	pub proof: PoiProof,
 }
 
-/// A not-yet-computed attempt to solve the proof of work. Calling the
-/// compute method will compute the hash and return the seal.
-#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
-pub struct Interact {
-	pub message: H256,
-	pub peer: H256,
+impl Seal {
+	/// Whether the proof was computed for the block built on `parent` whose header hashes, without
+	/// the seal, to `pre_hash`: the dependency must be the parent and the message the pre-hash.
+	///
+	/// As the pre-hash covers the extrinsics root, a proof cannot be moved to another block with
+	/// the same parent.
+	pub fn commits_to(&self, parent: &H256, pre_hash: &H256) -> bool {
+		self.proof.dependency == *parent && self.proof.message == *pre_hash
+	}
 }
 
-fn _client(url: &str) -> Result<Client, simple_http::Error> {
-	let t = SimpleHttpTransport::builder().url(url)?.build();
-
-	Ok(Client::with_transport(t))
+/// The PoI parameters of the tours sealing the children of block `parent`.
+pub fn parameters_at<B, C>(client: &C, parent: B::Hash) -> Result<PoiParameters, ApiError>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B>,
+	C::Api: PoiParametersApi<B>,
+{
+	client.runtime_api().parameters(parent)
 }
 
-impl Interact {
-	pub fn compute<C, B: BlockT<Hash = H256>>(self, sc_client: Arc<C>, at: B::Hash) -> Seal
-	where
-		C: ProvideRuntimeApi<B>,
-		C::Api: AuthorityDiscoveryApi<B>,
-	{
-		let work = H256::from_slice(Sha3_256::digest(&self.encode()[..]).as_slice());
-
-		let authorities = sc_client
-			.runtime_api().authorities(at).unwrap();
-
-		// println!("PoW authorities: {:?}", authorities[0]);
-
-		// let client =
-		// 	_client("http://api.random.org/json-rpc/1/invoke").expect("failed to create client");
-		// let request = client.build_request("uptime", &[]);
-		// let response = client.send_request(request).expect("send_request failed");
-
-		// // For other commands this would be a struct matching the returned json.
-		// let result: u64 = response
-		// 	.result()
-		// 	.expect("response is an error, use check_error");
-
-		// println!("bitcoind uptime: {}", result);
-
-		Seal {
-			nonce: self.nonce,
-			difficulty: self.difficulty,
-			work,
-			authorities,
-		}
+/// Check the proof carried by `seal` against the PoI `keys` of the authorities of its parent
+/// block and the PoI `parameters` at that block, for the chain starting at `genesis_hash`.
+pub fn check_seal(
+	seal: &Seal,
+	genesis_hash: &H256,
+	keys: &[PoiId],
+	parameters: &PoiParameters,
+) -> bool {
+	let network = network_nodes(keys);
+
+	// Services are drawn from half of the network, which must thus hold at least two nodes.
+	if network.len() < 2 || !keys.contains(&seal.initiator) {
+		return false;
 	}
+
+	check_poi(&seal.proof, genesis_hash, seal.initiator.as_ref(), parameters, &network)
 }
 
-/// A minimal PoW algorithm that uses Sha3 hashing.
-/// Difficulty is fixed at 1_000_000
-pub struct MinimalSha3Algorithm<C> {
+/// The Proof-of-Interaction algorithm.
+/// Needs a reference to the client so it can grab the PoI parameters and the authorities,
+/// whose nodes make up the network, from the runtime.
+///
+/// The difficulty is the mean tour length of the parameters, in billionths of a hop.
+pub struct PoiAlgorithm<C> {
 	client: Arc<C>,
 }
 
-impl<C> MinimalSha3Algorithm<C> {
+impl<C> PoiAlgorithm<C> {
 	pub fn new(client: Arc<C>) -> Self {
 		Self { client }
 	}
 }
 
 // Manually implement clone. Deriving doesn't work because
-// it'll derive impl<C: Clone> Clone for Sha3Algorithm<C>. But C in practice isn't Clone.
-impl<C> Clone for MinimalSha3Algorithm<C> {
+// it'll derive impl<C: Clone> Clone for PoiAlgorithm<C>. But C in practice isn't Clone.
+impl<C> Clone for PoiAlgorithm<C> {
 	fn clone(&self) -> Self {
 		Self::new(self.client.clone())
 	}
 }
 
-// Here we implement the general PowAlgorithm trait for our concrete Sha3Algorithm
-impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for MinimalSha3Algorithm<C> {
+impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for PoiAlgorithm<C>
+where
+	C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+	C::Api: PoiParametersApi<B> + PoiApi<B>,
+{
 	type Difficulty = U256;
 
-	fn difficulty(&self, _parent: B::Hash) -> Result<Self::Difficulty, Error<B>> {
-		// Fixed difficulty hardcoded here
-		Ok(U256::from(100))
+	fn difficulty(&self, parent: B::Hash) -> Result<Self::Difficulty, Error<B>> {
+		let parameters = self.parameters::<B>(parent)?;
+		Ok(U256::from(parameters.difficulty.into_inner()))
 	}
 
 	fn verify(
 		&self,
-		_parent: &BlockId<B>,
-		_pre_hash: &H256,
+		parent: &BlockId<B>,
+		pre_hash: &H256,
 		_pre_digest: Option<&[u8]>,
 		seal: &RawSeal,
-		difficulty: Self::Difficulty,
+		_difficulty: Self::Difficulty,
 	) -> Result<bool, Error<B>> {
 		// Try to construct a seal object by decoding the raw seal given
 		let seal = match Seal::decode(&mut &seal[..]) {
@@ -122,96 +117,237 @@ impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for MinimalSha3Algorithm<C> {
 			Err(_) => return Ok(false),
 		};
 
-		// See whether the hash meets the difficulty requirement. If not, fail fast.
-		if !hash_meets_difficulty(&seal.work, difficulty) {
-			return Ok(false);
+		let parent = self.client.expect_block_hash_from_id(parent).map_err(|err| {
+			sc_consensus_pow::Error::Environment(format!(
+				"Looking up parent block failed: {:?}",
+				err
+			))
+		})?;
+
+		// The proof must have been computed over this very block
+		if !seal.commits_to(&parent, pre_hash) {
+			return Ok(false)
 		}
 
-		// Make sure the provided work actually comes from the correct pre_hash
-		// let compute = Compute {
-		// 	difficulty,
-		// 	pre_hash: *pre_hash,
-		// 	nonce: seal.nonce,
-		// };
+		// The network the tour was drawn from is made of the authorities at the parent block
+		let authorities =
+			network::authorities_at::<B, _>(&*self.client, parent).map_err(|err| {
+				sc_consensus_pow::Error::Environment(format!(
+					"Fetching authorities from runtime failed: {:?}",
+					err
+				))
+			})?;
+
+		let keys: Vec<PoiId> = authorities.into_iter().map(|(_, key)| key).collect();
 
-		// if compute.compute().await != seal {
-		// 	return Ok(false);
-		// }
+		// The difficulty is one of the parameters, which the whole proof is checked against
+		let parameters = self.parameters::<B>(parent)?;
 
-		Ok(true)
+		Ok(check_seal(&seal, &self.client.info().genesis_hash, &keys, &parameters))
 	}
 }
 
-/// A complete PoW Algorithm that uses Sha3 hashing.
-/// Needs a reference to the client so it can grab the difficulty from the runtime.
-pub struct Sha3Algorithm<C> {
-	client: Arc<C>,
+impl<C> PoiAlgorithm<C> {
+	fn parameters<B>(&self, parent: B::Hash) -> Result<PoiParameters, Error<B>>
+	where
+		B: BlockT,
+		C: ProvideRuntimeApi<B>,
+		C::Api: PoiParametersApi<B>,
+	{
+		parameters_at::<B, _>(&*self.client, parent).map_err(|err| {
+			sc_consensus_pow::Error::Environment(format!(
+				"Fetching PoI parameters from runtime failed: {:?}",
+				err
+			))
+		})
+	}
 }
 
-impl<C> Sha3Algorithm<C> {
-	pub fn new(client: Arc<C>) -> Self {
-		Self { client }
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use poi_core::{generate_poi, FixedU64, PoiError, TourConfig};
+	use sp_core::{sr25519, Pair, H512};
+
+	const GENESIS_HASH: H256 = H256::repeat_byte(9);
+
+	fn pairs(count: u8) -> Vec<sr25519::Pair> {
+		(1..=count).map(|i| sr25519::Pair::from_seed(&[i; 32])).collect()
 	}
-}
 
-// Manually implement clone. Deriving doesn't work because
-// it'll derive impl<C: Clone> Clone for Sha3Algorithm<C>. But C in practice isn't Clone.
-impl<C> Clone for Sha3Algorithm<C> {
-	fn clone(&self) -> Self {
-		Self::new(self.client.clone())
+	fn authorities(pairs: &[sr25519::Pair]) -> Vec<PoiId> {
+		pairs.iter().map(|pair| PoiId::from(pair.public())).collect()
 	}
-}
 
-// Here we implement the general PowAlgorithm trait for our concrete Sha3Algorithm
-impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for Sha3Algorithm<C>
-where
-	C: ProvideRuntimeApi<B>,
-	C::Api: DifficultyApi<B, U256>,
-{
-	type Difficulty = U256;
+	/// Let `pairs[0]` generate a proof over `pairs`, every visited node signing its challenge
+	/// the way the signing protocol would.
+	fn seal(pairs: &[sr25519::Pair]) -> Seal {
+		let initiator = &pairs[0];
+		let network = network_nodes(&authorities(pairs));
+
+		let proof = futures::executor::block_on(generate_poi(
+			initiator,
+			GENESIS_HASH,
+			H256::repeat_byte(1),
+			0,
+			H256::repeat_byte(2),
+			&PoiParameters::default(),
+			&network,
+			&TourConfig::default(),
+			|node, challenge| {
+				let pair = pairs.iter().find(|pair| pair.public() == *node.get_public_key());
+				futures::future::ready(
+					pair.map(|pair| H512::from(pair.sign(&challenge.to_message()).0))
+						.ok_or(PoiError::PeerUnreachable),
+				)
+			},
+		))
+		.unwrap();
+
+		Seal { initiator: PoiId::from(initiator.public()), proof }
+	}
 
-	fn difficulty(&self, parent: B::Hash) -> Result<Self::Difficulty, Error<B>> {
-		self.client
-			.runtime_api()
-			.difficulty(parent)
-			.map_err(|err| {
-				sc_consensus_pow::Error::Environment(format!(
-					"Fetching difficulty from runtime failed: {:?}",
-					err
-				))
-			})
+	#[test]
+	fn seal_round_trips_through_raw_seal() {
+		let seal = seal(&pairs(6));
+		let raw: RawSeal = seal.encode();
+
+		assert_eq!(Seal::decode(&mut &raw[..]).unwrap(), seal);
 	}
 
-	fn verify(
-		&self,
-		_parent: &BlockId<B>,
-		_pre_hash: &H256,
-		_pre_digest: Option<&[u8]>,
-		seal: &RawSeal,
-		difficulty: Self::Difficulty,
-	) -> Result<bool, Error<B>> {
-		// Try to construct a seal object by decoding the raw seal given
-		let seal = match Seal::decode(&mut &seal[..]) {
-			Ok(seal) => seal,
-			Err(_) => return Ok(false),
-		};
+	#[test]
+	fn accepts_proof_signed_by_the_visited_nodes() {
+		let pairs = pairs(6);
+		let seal = seal(&pairs);
 
-		// See whether the hash meets the difficulty requirement. If not, fail fast.
-		if !hash_meets_difficulty(&seal.work, difficulty) {
-			return Ok(false);
+		assert!(check_seal(&seal, &GENESIS_HASH, &authorities(&pairs), &PoiParameters::default()));
+	}
+
+	#[test]
+	fn rejects_tampered_signatures() {
+		let pairs = pairs(6);
+		let authorities = authorities(&pairs);
+		let valid = seal(&pairs);
+
+		let mut tampered = valid.clone();
+		tampered.proof.seed.output.0[0] ^= 1;
+		assert!(!check_seal(&tampered, &GENESIS_HASH, &authorities, &PoiParameters::default()));
+
+		let mut tampered = valid.clone();
+		tampered.proof.seed.proof.0[0] ^= 1;
+		assert!(!check_seal(&tampered, &GENESIS_HASH, &authorities, &PoiParameters::default()));
+
+		for i in 0..valid.proof.hops.len() {
+			let mut tampered = valid.clone();
+			tampered.proof.hops.get_mut(i).unwrap().signature.0[0] ^= 1;
+			assert!(
+				!check_seal(&tampered, &GENESIS_HASH, &authorities, &PoiParameters::default()),
+				"hop {}",
+				i
+			);
+
+			let mut tampered = valid.clone();
+			tampered.proof.hops.get_mut(i).unwrap().counter_signature.0[0] ^= 1;
+			assert!(
+				!check_seal(&tampered, &GENESIS_HASH, &authorities, &PoiParameters::default()),
+				"hop {}'",
+				i
+			);
 		}
+	}
+
+	#[test]
+	fn rejects_proof_for_other_dependency_message_or_chain() {
+		let pairs = pairs(6);
+		let authorities = authorities(&pairs);
+
+		let mut other_dependency = seal(&pairs);
+		other_dependency.proof.dependency = H256::repeat_byte(3);
+		assert!(!check_seal(
+			&other_dependency,
+			&GENESIS_HASH,
+			&authorities,
+			&PoiParameters::default()
+		));
+
+		let mut other_message = seal(&pairs);
+		other_message.proof.message = H256::repeat_byte(3);
+		assert!(!check_seal(
+			&other_message,
+			&GENESIS_HASH,
+			&authorities,
+			&PoiParameters::default()
+		));
+
+		let other_chain = seal(&pairs);
+		assert!(!check_seal(
+			&other_chain,
+			&H256::repeat_byte(8),
+			&authorities,
+			&PoiParameters::default()
+		));
+	}
+
+	#[test]
+	fn seal_commits_to_its_parent_and_pre_hash() {
+		let seal = seal(&pairs(6));
+		let (parent, pre_hash) = (H256::repeat_byte(1), H256::repeat_byte(2));
+
+		assert!(seal.commits_to(&parent, &pre_hash));
+		assert!(!seal.commits_to(&H256::repeat_byte(3), &pre_hash));
+		assert!(!seal.commits_to(&parent, &H256::repeat_byte(3)));
+		assert!(!seal.commits_to(&pre_hash, &parent));
+	}
+
+	#[test]
+	fn rejects_proof_checked_with_other_parameters() {
+		let pairs = pairs(6);
+		let authorities = authorities(&pairs);
+		let seal = seal(&pairs);
+
+		let harder = PoiParameters {
+			difficulty: FixedU64::saturating_from_integer(40),
+			min_tour_length: FixedU64::saturating_from_integer(4),
+			..Default::default()
+		};
+		assert!(!check_seal(&seal, &GENESIS_HASH, &authorities, &harder));
+	}
+
+	#[test]
+	fn rejects_proof_signed_by_another_initiator() {
+		let pairs = pairs(6);
+		let authorities = authorities(&pairs);
+
+		let mut stolen = seal(&pairs);
+		stolen.initiator = authorities[1].clone();
+		assert!(!check_seal(&stolen, &GENESIS_HASH, &authorities, &PoiParameters::default()));
+	}
+
+	#[test]
+	fn rejects_proof_of_wrong_length() {
+		let pairs = pairs(6);
+		let authorities = authorities(&pairs);
+
+		let mut long = seal(&pairs);
+		let last = *long.proof.hops.last().unwrap();
+		long.proof.push_hop(last).unwrap();
+		assert!(!check_seal(&long, &GENESIS_HASH, &authorities, &PoiParameters::default()));
+
+		let mut short = seal(&pairs);
+		short.proof.hops.truncate(short.proof.hops.len() - 1);
+		assert!(!check_seal(&short, &GENESIS_HASH, &authorities, &PoiParameters::default()));
+	}
 
-		// Make sure the provided work actually comes from the correct pre_hash
-		// let compute = Compute {
-		// 	difficulty,
-		// 	pre_hash: *pre_hash,
-		// 	nonce: seal.nonce,
-		// };
+	#[test]
+	fn rejects_unknown_initiator_and_tiny_networks() {
+		let pairs = pairs(6);
+		let authorities = authorities(&pairs);
 
-		// if compute.compute() != seal {
-		// 	return Ok(false);
-		// }
+		let mut stranger = seal(&pairs);
+		stranger.initiator = PoiId::from(sr25519::Public::from_raw([99; 32]));
+		assert!(!check_seal(&stranger, &GENESIS_HASH, &authorities, &PoiParameters::default()));
 
-		Ok(true)
+		let lonely = seal(&pairs[..2]);
+		assert!(!check_seal(&lonely, &GENESIS_HASH, &authorities[..1], &PoiParameters::default()));
 	}
 }
@@ -0,0 +1,114 @@
+//! The network set N tours are drawn from.
+//!
+//! N is made of the authorities at the parent of the block being sealed, sorted by PoI key so
+//! that every node draws the same services from it, whatever order the runtime returned them in.
+//! Authority discovery tells where each of them can be reached.
+
+use pallet_poi_runtime_api::PoiApi;
+use poi_core::{Node, PoiId};
+use sc_authority_discovery::Service as AuthorityDiscoveryService;
+use sp_api::{ApiError, ProvideRuntimeApi};
+use sp_authority_discovery::AuthorityId;
+use sp_core::{hexdisplay::HexDisplay, sr25519};
+use sp_runtime::traits::Block as BlockT;
+
+/// An authority, reached through its authority discovery key and signing with its PoI key.
+pub type Authority = (AuthorityId, PoiId);
+
+/// `authorities` sorted by PoI key, without duplicates.
+pub fn sorted_authorities(authorities: &[Authority]) -> Vec<Authority> {
+	let mut sorted = authorities.to_vec();
+	sorted.sort_by(|(_, a), (_, b)| a.cmp(b));
+	sorted.dedup_by(|(_, a), (_, b)| a == b);
+	sorted
+}
+
+/// The sorted authorities at block `parent`.
+pub fn authorities_at<B, C>(client: &C, parent: B::Hash) -> Result<Vec<Authority>, ApiError>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B>,
+	C::Api: PoiApi<B>,
+{
+	let authorities = client.runtime_api().authorities(parent)?;
+	Ok(sorted_authorities(&authorities))
+}
+
+/// The network set N, one node per PoI key, named after the hex-encoded key. Nodes have no
+/// address, which is all a proof check needs.
+pub fn network_nodes(keys: &[PoiId]) -> Vec<Node> {
+	let mut keys = keys.to_vec();
+	keys.sort();
+	keys.dedup();
+
+	keys.iter().map(|key| node(key, String::new())).collect()
+}
+
+/// The network set N along with the address authority discovery knows for each node.
+///
+/// A node published under several addresses is given the smallest one, and an empty address
+/// when none is known yet. It stays part of N nonetheless, since other nodes may know it.
+pub async fn resolve_network_nodes(
+	authorities: &[Authority],
+	discovery: &mut AuthorityDiscoveryService,
+) -> Vec<Node> {
+	let mut nodes = Vec::new();
+
+	for (authority, key) in sorted_authorities(authorities) {
+		let address = discovery
+			.get_addresses_by_authority_id(authority)
+			.await
+			.and_then(|addresses| addresses.into_iter().min())
+			.map(|address| address.to_string())
+			.unwrap_or_default();
+		nodes.push(node(&key, address));
+	}
+
+	nodes
+}
+
+fn node(key: &PoiId, address: String) -> Node {
+	let key: &sr25519::Public = key.as_ref();
+	Node::new(format!("{}", HexDisplay::from(&key.0)), address, *key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key(byte: u8) -> PoiId {
+		PoiId::from(sr25519::Public::from_raw([byte; 32]))
+	}
+
+	fn authority(byte: u8, key_byte: u8) -> Authority {
+		(AuthorityId::from(sr25519::Public::from_raw([byte; 32])), key(key_byte))
+	}
+
+	#[test]
+	fn network_does_not_depend_on_keys_order() {
+		let keys = |poi_keys: &[PoiId]| -> Vec<sr25519::Public> {
+			network_nodes(poi_keys).iter().map(|node| *node.get_public_key()).collect()
+		};
+
+		let sorted = keys(&[key(1), key(2), key(3)]);
+		assert_eq!(sorted, keys(&[key(3), key(1), key(2)]));
+		assert_eq!(sorted, keys(&[key(2), key(3), key(1), key(2)]));
+		assert_eq!(sorted[0], sr25519::Public::from_raw([1; 32]));
+	}
+
+	#[test]
+	fn authorities_are_sorted_by_poi_key() {
+		let sorted = sorted_authorities(&[authority(1, 3), authority(2, 1), authority(3, 2)]);
+
+		assert_eq!(sorted, vec![authority(2, 1), authority(3, 2), authority(1, 3)]);
+		assert_eq!(sorted_authorities(&[authority(1, 1), authority(2, 1)]).len(), 1);
+	}
+
+	#[test]
+	fn nodes_are_named_after_their_key() {
+		let nodes = network_nodes(&[key(0xab)]);
+
+		assert_eq!(nodes[0].get_name(), "ab".repeat(32));
+		assert_eq!(nodes[0].get_ip(), "");
+	}
+}
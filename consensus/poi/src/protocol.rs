@@ -0,0 +1,482 @@
+//! The `/ambula/poi/sign/1` request-response protocol.
+//!
+//! An initiator touring the network asks every service it visits to sign the challenge of the
+//! hop over the peer-to-peer connections authority discovery establishes, so validators need
+//! not expose their RPC. A request names the initiator and the PoI key expected to sign along
+//! with the challenge, and the answer is the SCALE-encoded signature.
+//!
+//! Services only sign challenges made for their own chain on top of a block they know, for an
+//! initiator and with a key both authorities at that block, and answer every peer a limited
+//! number of times per minute.
+
+use crate::{network, rate_limit::RateLimiter};
+use futures::{channel::mpsc, StreamExt};
+use log::debug;
+use pallet_poi_runtime_api::PoiApi;
+use parity_scale_codec::{Decode, Encode};
+use poi_core::{Challenge, PoiError, PoiId, KEY_TYPE, MAX_HOPS};
+use sc_network::{
+	config::{IncomingRequest, OutgoingResponse, RequestResponseConfig},
+	multiaddr::Protocol,
+	IfDisconnected, Multiaddr, NetworkPeers, NetworkRequest, PeerId,
+};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{H256, H512};
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+use sp_runtime::traits::Block as BlockT;
+use std::{fmt, marker::PhantomData, sync::Arc, time::Duration};
+
+const LOG_TARGET: &str = "poi-sign";
+
+/// Name of the protocol.
+pub const PROTOCOL_NAME: &str = "/ambula/poi/sign/1";
+
+/// Requests hold two PoI keys and a challenge.
+const MAX_REQUEST_SIZE: u64 = 256;
+/// Responses hold a signature.
+const MAX_RESPONSE_SIZE: u64 = 128;
+/// Timeout of a request, as enforced by the network.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Requests waiting for the handler before new ones are refused.
+const INBOUND_QUEUE_SIZE: usize = 64;
+
+/// Times an initiator asks for the signature of a hop, that is once and then up to the default
+/// `max_retries` of its tours.
+const TRIES_PER_HOP: u32 = 3;
+/// Tours of [`MAX_HOPS`] hops a peer may walk through this node alone per [`RATE_LIMIT_PERIOD`], as
+/// a tour may visit a single service: tours restart on new best blocks and after failed attempts,
+/// and several initiators may share a peer.
+const TOURS_PER_PERIOD: u32 = 4;
+/// Signatures a peer may ask for per [`RATE_LIMIT_PERIOD`].
+pub const MAX_REQUESTS_PER_PEER: u32 = MAX_HOPS * TRIES_PER_HOP * TOURS_PER_PERIOD;
+/// Period over which [`MAX_REQUESTS_PER_PEER`] is counted.
+pub const RATE_LIMIT_PERIOD: Duration = Duration::from_secs(60);
+
+/// Asks `signer` to sign the challenge of a hop of the tour of `initiator`.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct SignRequest {
+	/// PoI key of the authority walking the tour.
+	pub initiator: PoiId,
+	/// PoI key expected to sign the challenge.
+	pub signer: PoiId,
+	/// The challenge of the hop.
+	pub challenge: Challenge,
+}
+
+/// Sign `message` with the PoI key `signer`, if the keystore holds it.
+pub fn sign_message(keystore: &SyncCryptoStorePtr, signer: &PoiId, message: &[u8]) -> Option<H512> {
+	let public: &sp_core::sr25519::Public = signer.as_ref();
+	let signature =
+		SyncCryptoStore::sign_with(&**keystore, KEY_TYPE, &(*public).into(), message).ok()??;
+
+	if signature.len() == H512::len_bytes() {
+		Some(H512::from_slice(&signature))
+	} else {
+		None
+	}
+}
+
+/// What a service knows of its chain, to only sign challenges made for it.
+pub trait ChainView: Send + Sync {
+	/// Hash of the genesis block of the chain.
+	fn genesis_hash(&self) -> H256;
+	/// Whether the block `hash` is known locally.
+	fn is_known(&self, hash: &H256) -> bool;
+	/// The PoI keys of the authorities at block `hash`, None if they cannot be fetched.
+	fn authorities(&self, hash: &H256) -> Option<Vec<PoiId>>;
+}
+
+/// The chain as seen by a client.
+pub struct ClientView<B, C> {
+	client: Arc<C>,
+	_block: PhantomData<B>,
+}
+
+impl<B, C> ClientView<B, C> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _block: PhantomData }
+	}
+}
+
+impl<B, C> ChainView for ClientView<B, C>
+where
+	B: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+	C::Api: PoiApi<B>,
+{
+	fn genesis_hash(&self) -> H256 {
+		self.client.info().genesis_hash
+	}
+
+	fn is_known(&self, hash: &H256) -> bool {
+		matches!(self.client.header(*hash), Ok(Some(_)))
+	}
+
+	fn authorities(&self, hash: &H256) -> Option<Vec<PoiId>> {
+		let authorities = network::authorities_at::<B, _>(&*self.client, *hash).ok()?;
+		Some(authorities.into_iter().map(|(_, key)| key).collect())
+	}
+}
+
+/// Why a service refuses to sign a challenge.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Refusal {
+	/// The request does not decode.
+	Malformed,
+	/// The challenge is made for another chain.
+	UnknownGenesis,
+	/// The parent block of the challenge, or its authorities, are not known locally.
+	UnknownParent,
+	/// The initiator is not an authority at the parent block of the challenge.
+	UnknownInitiator,
+	/// The peer asked for too many signatures lately.
+	RateLimited,
+	/// The keystore does not hold the requested key, or it is not an authority at the parent
+	/// block of the challenge.
+	UnknownKey,
+}
+
+impl fmt::Display for Refusal {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Refusal::Malformed => "malformed request",
+			Refusal::UnknownGenesis => "challenge made for another chain",
+			Refusal::UnknownParent => "challenge made on top of an unknown block",
+			Refusal::UnknownInitiator => "initiator is not an authority",
+			Refusal::RateLimited => "too many requests",
+			Refusal::UnknownKey => "PoI key not held by this node",
+		};
+		f.write_str(description)
+	}
+}
+
+/// Signs the challenges made for the chain seen by `V` with the PoI keys of a keystore.
+pub struct ChallengeSigner<V> {
+	chain: V,
+	keystore: SyncCryptoStorePtr,
+}
+
+impl<V: ChainView> ChallengeSigner<V> {
+	pub fn new(chain: V, keystore: SyncCryptoStorePtr) -> Self {
+		Self { chain, keystore }
+	}
+
+	/// Check that `challenge` is made for the local chain, on top of a known block, for a tour
+	/// of `initiator`, an authority at that block. Returns the authorities at that block.
+	pub fn check(&self, initiator: &PoiId, challenge: &Challenge) -> Result<Vec<PoiId>, Refusal> {
+		if challenge.genesis_hash != self.chain.genesis_hash() {
+			return Err(Refusal::UnknownGenesis)
+		}
+		if !self.chain.is_known(&challenge.parent_hash) {
+			return Err(Refusal::UnknownParent)
+		}
+
+		let authorities =
+			self.chain.authorities(&challenge.parent_hash).ok_or(Refusal::UnknownParent)?;
+		if !authorities.contains(initiator) {
+			return Err(Refusal::UnknownInitiator)
+		}
+		Ok(authorities)
+	}
+
+	/// Check `challenge`, then sign it with the PoI key `signer`, which must be an authority at
+	/// the parent block of the challenge. Without `signer`, the challenge is signed with the key
+	/// the keystore holds for such an authority.
+	pub fn sign(
+		&self,
+		initiator: &PoiId,
+		signer: Option<&PoiId>,
+		challenge: &Challenge,
+	) -> Result<H512, Refusal> {
+		let authorities = self.check(initiator, challenge)?;
+		let signer = match signer {
+			Some(signer) if authorities.contains(signer) => signer.clone(),
+			Some(_) => return Err(Refusal::UnknownKey),
+			None => self.local_authority(&authorities).ok_or(Refusal::UnknownKey)?,
+		};
+
+		sign_message(&self.keystore, &signer, &challenge.to_message()).ok_or(Refusal::UnknownKey)
+	}
+
+	fn local_authority(&self, authorities: &[PoiId]) -> Option<PoiId> {
+		let keys = SyncCryptoStore::sr25519_public_keys(&*self.keystore, KEY_TYPE);
+		authorities.iter().find(|key| keys.contains(key.as_ref())).cloned()
+	}
+}
+
+/// Answers the signing requests of other authorities.
+pub struct SignRequestHandler<V> {
+	signer: ChallengeSigner<V>,
+	limiter: RateLimiter<PeerId>,
+	requests: mpsc::Receiver<IncomingRequest>,
+}
+
+impl<V: ChainView> SignRequestHandler<V> {
+	/// A handler answering with `signer`, along with the configuration registering the protocol
+	/// on the network.
+	pub fn new(signer: ChallengeSigner<V>) -> (Self, RequestResponseConfig) {
+		let (sender, requests) = mpsc::channel(INBOUND_QUEUE_SIZE);
+		let config = RequestResponseConfig {
+			name: PROTOCOL_NAME.into(),
+			fallback_names: Vec::new(),
+			max_request_size: MAX_REQUEST_SIZE,
+			max_response_size: MAX_RESPONSE_SIZE,
+			request_timeout: REQUEST_TIMEOUT,
+			inbound_queue: Some(sender),
+		};
+		let limiter = RateLimiter::new(MAX_REQUESTS_PER_PEER, RATE_LIMIT_PERIOD);
+
+		(Self { signer, limiter, requests }, config)
+	}
+
+	/// Answer requests until the network shuts down.
+	pub async fn run(mut self) {
+		while let Some(IncomingRequest { peer, payload, pending_response }) =
+			self.requests.next().await
+		{
+			let result = self.handle(peer, &payload).map_err(|refusal| {
+				debug!(target: LOG_TARGET, "Refused signing request from {}: {}", peer, refusal);
+			});
+
+			let response =
+				OutgoingResponse { result, reputation_changes: Vec::new(), sent_feedback: None };
+			if pending_response.send(response).is_err() {
+				debug!(target: LOG_TARGET, "Failed to answer signing request from {}", peer);
+			}
+		}
+	}
+
+	fn handle(&self, peer: PeerId, payload: &[u8]) -> Result<Vec<u8>, Refusal> {
+		if !self.limiter.check(peer) {
+			return Err(Refusal::RateLimited)
+		}
+
+		let request = SignRequest::decode(&mut &payload[..]).map_err(|_| Refusal::Malformed)?;
+		let signature =
+			self.signer
+				.sign(&request.initiator, Some(&request.signer), &request.challenge)?;
+
+		Ok(signature.encode())
+	}
+}
+
+/// The peer behind `address`, which authority discovery always ends with `/p2p/<peer id>`.
+pub fn peer_id(address: &Multiaddr) -> Option<PeerId> {
+	address.iter().find_map(|protocol| match protocol {
+		Protocol::P2p(hash) => PeerId::from_multihash(hash).ok(),
+		_ => None,
+	})
+}
+
+/// Ask the node reached at `address`, as resolved through authority discovery, to sign the
+/// challenge of `request`.
+///
+/// The signature is not checked, which is left to the tour.
+pub async fn request_signature<N>(
+	network: &N,
+	address: String,
+	request: SignRequest,
+) -> Result<H512, PoiError>
+where
+	N: NetworkRequest + NetworkPeers,
+{
+	let address: Multiaddr = address.parse().map_err(|_| {
+		debug!(target: LOG_TARGET, "No address known for {:?}", request.signer);
+		PoiError::ParseFailure
+	})?;
+	let peer = peer_id(&address).ok_or(PoiError::ParseFailure)?;
+	network.add_known_address(peer, address);
+
+	let response = network
+		.request(peer, PROTOCOL_NAME.into(), request.encode(), IfDisconnected::TryConnect)
+		.await
+		.map_err(|e| {
+			debug!(target: LOG_TARGET, "Signing request to {} failed: {}", peer, e);
+			PoiError::PeerUnreachable
+		})?;
+
+	H512::decode(&mut &response[..]).map_err(|_| PoiError::MalformedResponse)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use poi_core::TourConfig;
+	use sp_core::{sr25519, Pair};
+	use sp_keystore::testing::KeyStore;
+
+	const GENESIS_HASH: H256 = H256::repeat_byte(9);
+
+	/// A chain made of its genesis block and the block `[1; 32]`, at which `authorities` are the
+	/// authorities.
+	struct TestChain {
+		authorities: Vec<PoiId>,
+	}
+
+	impl ChainView for TestChain {
+		fn genesis_hash(&self) -> H256 {
+			GENESIS_HASH
+		}
+
+		fn is_known(&self, hash: &H256) -> bool {
+			*hash == GENESIS_HASH || *hash == H256::repeat_byte(1)
+		}
+
+		fn authorities(&self, hash: &H256) -> Option<Vec<PoiId>> {
+			(*hash == H256::repeat_byte(1)).then(|| self.authorities.clone())
+		}
+	}
+
+	fn initiator() -> PoiId {
+		PoiId::from(sr25519::Pair::from_seed(&[2; 32]).public())
+	}
+
+	fn challenge() -> Challenge {
+		Challenge {
+			genesis_hash: GENESIS_HASH,
+			parent_hash: H256::repeat_byte(1),
+			hop: 3,
+			current_hash: H256::repeat_byte(2),
+		}
+	}
+
+	fn request(signer: PoiId) -> SignRequest {
+		SignRequest { initiator: initiator(), signer, challenge: challenge() }
+	}
+
+	/// A signer holding a single PoI key, which is returned along, and the initiator key as
+	/// authorities, along with `others`.
+	fn signer(others: &[PoiId]) -> (ChallengeSigner<TestChain>, sr25519::Public) {
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let public = SyncCryptoStore::sr25519_generate_new(&*keystore, KEY_TYPE, None).unwrap();
+		let mut authorities = vec![initiator(), PoiId::from(public)];
+		authorities.extend_from_slice(others);
+
+		(ChallengeSigner::new(TestChain { authorities }, keystore), public)
+	}
+
+	/// A handler holding a single PoI key, which is returned along.
+	fn handler() -> (SignRequestHandler<TestChain>, sr25519::Public) {
+		let (signer, public) = signer(&[]);
+		let (handler, _) = SignRequestHandler::new(signer);
+
+		(handler, public)
+	}
+
+	#[test]
+	fn request_fits_the_size_limit() {
+		let request = request(PoiId::from(sr25519::Public::from_raw([0; 32])));
+
+		assert!(request.encode().len() as u64 <= MAX_REQUEST_SIZE);
+		assert!(H512::zero().encode().len() as u64 <= MAX_RESPONSE_SIZE);
+	}
+
+	#[test]
+	fn handler_signs_the_hop_challenge_with_the_requested_key() {
+		let (handler, public) = handler();
+
+		let request = request(PoiId::from(public));
+		let response = handler.handle(PeerId::random(), &request.encode()).unwrap();
+		let signature = H512::decode(&mut &response[..]).unwrap();
+
+		assert!(poi_core::verify_signature(&public, &signature, &request.challenge.to_message()));
+	}
+
+	#[test]
+	fn handler_refuses_unknown_keys_and_garbage() {
+		let (handler, _) = handler();
+		let peer = PeerId::random();
+
+		let stranger = sr25519::Pair::from_seed(&[1; 32]).public();
+		assert_eq!(
+			handler.handle(peer, &request(PoiId::from(stranger)).encode()),
+			Err(Refusal::UnknownKey)
+		);
+		assert_eq!(handler.handle(peer, b"garbage"), Err(Refusal::Malformed));
+	}
+
+	#[test]
+	fn handler_refuses_challenges_of_other_chains_and_blocks() {
+		let (handler, public) = handler();
+		let peer = PeerId::random();
+
+		let mut request = request(PoiId::from(public));
+		request.challenge.genesis_hash = H256::repeat_byte(3);
+		assert_eq!(handler.handle(peer, &request.encode()), Err(Refusal::UnknownGenesis));
+
+		request.challenge.genesis_hash = GENESIS_HASH;
+		request.challenge.parent_hash = H256::repeat_byte(3);
+		assert_eq!(handler.handle(peer, &request.encode()), Err(Refusal::UnknownParent));
+	}
+
+	#[test]
+	fn handler_only_signs_for_authorities() {
+		let (handler, public) = handler();
+		let peer = PeerId::random();
+
+		let mut request = request(PoiId::from(public));
+		request.initiator = PoiId::from(sr25519::Pair::from_seed(&[3; 32]).public());
+		assert_eq!(handler.handle(peer, &request.encode()), Err(Refusal::UnknownInitiator));
+	}
+
+	#[test]
+	fn signer_only_signs_with_keys_of_authorities() {
+		// The keystore holds a key that is not an authority
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let public = SyncCryptoStore::sr25519_generate_new(&*keystore, KEY_TYPE, None).unwrap();
+		let signer = ChallengeSigner::new(TestChain { authorities: vec![initiator()] }, keystore);
+
+		let public = PoiId::from(public);
+		assert_eq!(
+			signer.sign(&initiator(), Some(&public), &challenge()),
+			Err(Refusal::UnknownKey)
+		);
+		assert_eq!(signer.sign(&initiator(), None, &challenge()), Err(Refusal::UnknownKey));
+	}
+
+	#[test]
+	fn signer_picks_its_authority_key() {
+		let (signer, public) = signer(&[PoiId::from(sr25519::Public::from_raw([7; 32]))]);
+
+		let signature = signer.sign(&initiator(), None, &challenge()).unwrap();
+		assert!(poi_core::verify_signature(&public, &signature, &challenge().to_message()));
+	}
+
+	#[test]
+	fn handler_limits_requests_per_peer() {
+		let (handler, public) = handler();
+		let request = request(PoiId::from(public)).encode();
+		let peer = PeerId::random();
+
+		for _ in 0..MAX_REQUESTS_PER_PEER {
+			assert!(handler.handle(peer, &request).is_ok());
+		}
+		assert_eq!(handler.handle(peer, &request), Err(Refusal::RateLimited));
+		assert!(handler.handle(PeerId::random(), &request).is_ok());
+	}
+
+	#[test]
+	fn limit_lets_a_tour_of_maximum_length_through() {
+		let (handler, public) = handler();
+		let request = request(PoiId::from(public)).encode();
+		let peer = PeerId::random();
+
+		// Every hop of the tour visits this node, and is asked for as many times as retries allow
+		assert!(TRIES_PER_HOP >= 1 + TourConfig::default().max_retries);
+		for _ in 0..MAX_HOPS * TRIES_PER_HOP {
+			assert!(handler.handle(peer, &request).is_ok());
+		}
+		// Leaving room for the next tour
+		assert!(handler.handle(peer, &request).is_ok());
+	}
+
+	#[test]
+	fn peer_id_is_read_from_the_address() {
+		let peer = PeerId::random();
+		let address: Multiaddr = format!("/ip4/127.0.0.1/tcp/30333/p2p/{}", peer).parse().unwrap();
+
+		assert_eq!(peer_id(&address), Some(peer));
+		assert_eq!(peer_id(&"/ip4/127.0.0.1/tcp/30333".parse().unwrap()), None);
+	}
+}
@@ -0,0 +1,571 @@
+//! Generating the proof of interaction that seals a block.
+//!
+//! The [`Prover`] tours the authorities at the parent of the block, asking each service for its
+//! signature through its [`Services`], the signing protocol at the addresses authority discovery
+//! knows for [`NetworkServices`]. [`Prover::run`] drives the mining worker with it. An attempt is
+//! abandoned with [`PoiError::Aborted`] as soon as the best block moves away from the parent, after
+//! which the miner restarts on the new best block. `s0` is the VRF output of the PoI key over the
+//! parent and the number of the attempt: a failed tour is followed by the next attempt, which
+//! visits other services, up to [`MAX_ATTEMPTS`] on a given parent.
+//! The initiator may be drawn among the services, in which case it signs its own hops.
+//!
+//! The tour is computed over the `pre_hash` of the block, the hash of its header without the
+//! seal, extrinsics root included, and depends on its parent: [`PoiAlgorithm`] rejects any
+//! other proof.
+//!
+//! [`PoiAlgorithm`]: crate::PoiAlgorithm
+
+use crate::{
+	network::{self, Authority},
+	parameters_at,
+	protocol::{self, SignRequest},
+	Seal,
+};
+use futures::{
+	future::{self, select, BoxFuture, Either},
+	FutureExt, StreamExt,
+};
+use futures_timer::Delay;
+use log::{debug, info, warn};
+use pallet_poi_parameters_runtime_api::PoiParametersApi;
+use pallet_poi_runtime_api::PoiApi;
+use parity_scale_codec::Encode;
+use poi_core::{
+	generate_poi, Node, PoiError, PoiId, PoiSeed, TourConfig, TourSigner, KEY_TYPE, MAX_ATTEMPTS,
+	SEED_DOMAIN,
+};
+use sc_authority_discovery::Service as AuthorityDiscoveryService;
+use sc_client_api::BlockchainEvents;
+use sc_consensus::JustificationSyncLink;
+use sc_consensus_pow::{MiningHandle, MiningMetadata, PowAlgorithm};
+use sc_network::{NetworkPeers, NetworkRequest};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{sr25519, H256, H512, U256};
+use sp_keystore::{
+	vrf::{VRFTranscriptData, VRFTranscriptValue},
+	SyncCryptoStore, SyncCryptoStorePtr,
+};
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+const LOG_TARGET: &str = "poi-prover";
+
+/// How long to wait before checking again for a block to seal when there is none.
+const IDLE_DELAY: Duration = Duration::from_millis(500);
+/// How long to wait before trying again after a tour failed.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Signs with the PoI key `authority`, held by `keystore`.
+pub struct KeystoreSigner {
+	keystore: SyncCryptoStorePtr,
+	authority: PoiId,
+}
+
+impl KeystoreSigner {
+	pub fn new(keystore: SyncCryptoStorePtr, authority: PoiId) -> Self {
+		Self { keystore, authority }
+	}
+}
+
+impl TourSigner for KeystoreSigner {
+	fn key(&self) -> sr25519::Public {
+		*self.authority.as_ref()
+	}
+
+	fn sign_message(&self, message: &[u8]) -> Option<H512> {
+		protocol::sign_message(&self.keystore, &self.authority, message)
+	}
+
+	fn vrf_seed(&self, genesis_hash: &H256, dependency: &H256, attempt: u32) -> Option<PoiSeed> {
+		// The transcript of `poi_core::seed_transcript`
+		let transcript = VRFTranscriptData {
+			label: SEED_DOMAIN,
+			items: vec![
+				("genesis_hash", VRFTranscriptValue::Bytes(genesis_hash.as_bytes().to_vec())),
+				("dependency", VRFTranscriptValue::Bytes(dependency.as_bytes().to_vec())),
+				("attempt", VRFTranscriptValue::U64(u64::from(attempt))),
+			],
+		};
+		let signature = SyncCryptoStore::sr25519_vrf_sign(
+			&*self.keystore,
+			KEY_TYPE,
+			self.authority.as_ref(),
+			transcript,
+		)
+		.ok()
+		.flatten()?;
+
+		Some(PoiSeed {
+			attempt,
+			output: H256::from(signature.output.to_bytes()),
+			proof: H512::from(signature.proof.to_bytes()),
+		})
+	}
+}
+
+/// The PoI key of the authority among `authorities` whose key `keystore` holds, if any.
+pub fn local_authority(keystore: &SyncCryptoStorePtr, authorities: &[Authority]) -> Option<PoiId> {
+	let keys = SyncCryptoStore::sr25519_public_keys(&**keystore, KEY_TYPE);
+
+	authorities
+		.iter()
+		.map(|(_, key)| key)
+		.find(|key| keys.contains(key.as_ref()))
+		.cloned()
+}
+
+/// Resolves once the best block is no longer `parent`, or the client shuts down.
+pub async fn best_block_changed<B, C>(client: Arc<C>, parent: B::Hash)
+where
+	B: BlockT,
+	C: BlockchainEvents<B> + HeaderBackend<B>,
+{
+	let mut imports = client.import_notification_stream();
+	if client.info().best_hash != parent {
+		return
+	}
+
+	while let Some(notification) = imports.next().await {
+		if notification.is_new_best && notification.hash != parent {
+			return
+		}
+	}
+}
+
+/// The services a tour visits.
+pub trait Services {
+	/// The network set N made of `authorities`, along with the address of each node.
+	fn resolve<'a>(&'a mut self, authorities: &'a [Authority]) -> BoxFuture<'a, Vec<Node>>;
+
+	/// Ask `node` to sign the challenge of `request`.
+	fn request_signature(
+		&self,
+		node: &Node,
+		request: SignRequest,
+	) -> BoxFuture<'_, Result<H512, PoiError>>;
+}
+
+/// Services reached with the signing protocol, at the addresses authority discovery knows.
+pub struct NetworkServices<N> {
+	network: Arc<N>,
+	discovery: AuthorityDiscoveryService,
+}
+
+impl<N> NetworkServices<N> {
+	pub fn new(network: Arc<N>, discovery: AuthorityDiscoveryService) -> Self {
+		Self { network, discovery }
+	}
+}
+
+impl<N> Services for NetworkServices<N>
+where
+	N: NetworkRequest + NetworkPeers + Send + Sync,
+{
+	fn resolve<'a>(&'a mut self, authorities: &'a [Authority]) -> BoxFuture<'a, Vec<Node>> {
+		network::resolve_network_nodes(authorities, &mut self.discovery).boxed()
+	}
+
+	fn request_signature(
+		&self,
+		node: &Node,
+		request: SignRequest,
+	) -> BoxFuture<'_, Result<H512, PoiError>> {
+		protocol::request_signature(&*self.network, node.get_ip().to_string(), request).boxed()
+	}
+}
+
+/// Computes the seals of the blocks this authority mines.
+pub struct Prover<B, C, S> {
+	client: Arc<C>,
+	services: S,
+	keystore: SyncCryptoStorePtr,
+	config: TourConfig,
+	_block: PhantomData<B>,
+}
+
+impl<B, C, S> Prover<B, C, S>
+where
+	B: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<B> + BlockchainEvents<B> + HeaderBackend<B>,
+	C::Api: PoiApi<B> + PoiParametersApi<B>,
+	S: Services,
+{
+	pub fn new(
+		client: Arc<C>,
+		services: S,
+		keystore: SyncCryptoStorePtr,
+		config: TourConfig,
+	) -> Self {
+		Self { client, services, keystore, config, _block: PhantomData }
+	}
+
+	/// Seal the blocks `worker` builds, for as long as the node runs.
+	///
+	/// A tour aborted by a new best block is restarted on the block the worker builds on top of
+	/// it. A failed one is followed, after a short delay, by the next attempt on the same parent,
+	/// and once [`MAX_ATTEMPTS`] failed the prover waits for another best block.
+	pub async fn run<A, L, Proof>(mut self, worker: MiningHandle<B, A, C, L, Proof>)
+	where
+		A: PowAlgorithm<B, Difficulty = U256>,
+		L: JustificationSyncLink<B>,
+		sp_api::TransactionFor<C, B>: Send + 'static,
+	{
+		// The parent the last tour was made on, and the next attempt on it
+		let mut attempts: Option<(B::Hash, u32)> = None;
+		loop {
+			let metadata = match worker.metadata() {
+				Some(metadata) => metadata,
+				None => {
+					Delay::new(IDLE_DELAY).await;
+					continue
+				},
+			};
+
+			let parent = metadata.best_hash;
+			let attempt = match attempts {
+				Some((hash, attempt)) if hash == parent => attempt,
+				_ => 0,
+			};
+			if attempt >= MAX_ATTEMPTS {
+				// Nothing to do until the worker builds on another best block
+				Delay::new(IDLE_DELAY).await;
+				continue
+			}
+
+			attempts = Some((parent, attempt));
+			match self.prove_block(&metadata, attempt).await {
+				Ok(seal) =>
+					if worker.submit(seal.encode()).await {
+						info!(target: LOG_TARGET, "Sealed a block on top of {}", parent);
+					} else {
+						warn!(target: LOG_TARGET, "Seal on top of {} rejected", parent);
+					},
+				Err(PoiError::Aborted) => {
+					debug!(target: LOG_TARGET, "Best block moved away from {}", parent);
+				},
+				Err(e) => {
+					warn!(
+						target: LOG_TARGET,
+						"Attempt {} on top of {} failed: {:?}",
+						attempt,
+						parent,
+						e
+					);
+					attempts = Some((parent, attempt + 1));
+					Delay::new(RETRY_DELAY).await;
+				},
+			}
+		}
+	}
+
+	/// Seal the block the mining worker is working on, as described by its `metadata`, at the
+	/// given `attempt` on its parent.
+	pub async fn prove_block(
+		&mut self,
+		metadata: &MiningMetadata<B::Hash, U256>,
+		attempt: u32,
+	) -> Result<Seal, PoiError> {
+		self.prove(metadata.best_hash, attempt, metadata.pre_hash).await
+	}
+
+	/// Seal a block built on `parent` with the tour of `attempt`, computed over `message`, the
+	/// `pre_hash` of the block, with the PoI parameters at `parent`.
+	///
+	/// Fails with [`PoiError::Aborted`] if the best block changes in the meantime.
+	pub async fn prove(
+		&mut self,
+		parent: B::Hash,
+		attempt: u32,
+		message: H256,
+	) -> Result<Seal, PoiError> {
+		let parent_changed = best_block_changed(self.client.clone(), parent);
+		let tour = self.tour(parent, attempt, message);
+
+		match select(Box::pin(tour), Box::pin(parent_changed)).await {
+			Either::Left((result, _)) => result,
+			Either::Right(_) => Err(PoiError::Aborted),
+		}
+	}
+
+	async fn tour(
+		&mut self,
+		parent: B::Hash,
+		attempt: u32,
+		message: H256,
+	) -> Result<Seal, PoiError> {
+		let authorities = network::authorities_at::<B, _>(&*self.client, parent).map_err(|e| {
+			warn!(target: LOG_TARGET, "Fetching authorities at {} failed: {}", parent, e);
+			PoiError::NotEnoughNodes
+		})?;
+		let parameters = parameters_at::<B, _>(&*self.client, parent).map_err(|e| {
+			warn!(target: LOG_TARGET, "Fetching PoI parameters at {} failed: {}", parent, e);
+			PoiError::NotEnoughNodes
+		})?;
+		let initiator =
+			local_authority(&self.keystore, &authorities).ok_or(PoiError::SigningFailed)?;
+		let nodes = self.services.resolve(&authorities).await;
+
+		let signer = KeystoreSigner::new(self.keystore.clone(), initiator.clone());
+		let services = &self.services;
+		let keystore = &self.keystore;
+		let requester = &initiator;
+		let proof = generate_poi(
+			&signer,
+			self.client.info().genesis_hash,
+			parent,
+			attempt,
+			message,
+			&parameters,
+			&nodes,
+			&self.config,
+			move |node, challenge| {
+				let service = PoiId::from(*node.get_public_key());
+				// The initiator may be drawn among the services, and then signs its hops itself
+				if service == *requester {
+					let signature =
+						protocol::sign_message(keystore, requester, &challenge.to_message());
+					return future::ready(signature.ok_or(PoiError::SigningFailed)).boxed()
+				}
+
+				let request =
+					SignRequest { initiator: requester.clone(), signer: service, challenge };
+				services.request_signature(node, request)
+			},
+		)
+		.await?;
+
+		Ok(Seal { initiator, proof })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use poi_core::PoiParameters;
+	use sc_block_builder::BlockBuilderProvider;
+	use sc_client_api::{
+		FinalityNotifications, ImportNotifications, StorageEventStream, StorageKey,
+	};
+	use sp_api::ApiRef;
+	use sp_authority_discovery::AuthorityId;
+	use sp_blockchain::{BlockStatus, Info};
+	use sp_consensus::BlockOrigin;
+	use sp_keystore::testing::KeyStore;
+	use sp_runtime::{key_types::AUTHORITY_DISCOVERY, traits::NumberFor};
+	use substrate_test_runtime_client::{
+		runtime::{Block as TestBlock, Header as TestHeader},
+		ClientBlockImportExt, DefaultTestClientBuilderExt, TestClient, TestClientBuilder,
+		TestClientBuilderExt,
+	};
+
+	fn authority(key: sr25519::Public) -> Authority {
+		(AuthorityId::from(sr25519::Public::from_raw([0; 32])), PoiId::from(key))
+	}
+
+	#[derive(Clone)]
+	struct TestApi {
+		authorities: Vec<Authority>,
+	}
+
+	sp_api::mock_impl_runtime_apis! {
+		impl PoiApi<TestBlock> for TestApi {
+			fn authorities(&self) -> Vec<Authority> {
+				self.authorities.clone()
+			}
+		}
+
+		impl PoiParametersApi<TestBlock> for TestApi {
+			fn parameters() -> PoiParameters {
+				PoiParameters::default()
+			}
+		}
+	}
+
+	/// A test client whose runtime has `authorities`.
+	struct TestChain {
+		client: Arc<TestClient>,
+		authorities: Vec<Authority>,
+	}
+
+	impl ProvideRuntimeApi<TestBlock> for TestChain {
+		type Api = TestApi;
+
+		fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+			TestApi { authorities: self.authorities.clone() }.into()
+		}
+	}
+
+	impl HeaderBackend<TestBlock> for TestChain {
+		fn header(&self, hash: H256) -> sp_blockchain::Result<Option<TestHeader>> {
+			self.client.header(hash)
+		}
+
+		fn info(&self) -> Info<TestBlock> {
+			self.client.info()
+		}
+
+		fn status(&self, hash: H256) -> sp_blockchain::Result<BlockStatus> {
+			self.client.status(hash)
+		}
+
+		fn number(&self, hash: H256) -> sp_blockchain::Result<Option<NumberFor<TestBlock>>> {
+			self.client.number(hash)
+		}
+
+		fn hash(&self, number: NumberFor<TestBlock>) -> sp_blockchain::Result<Option<H256>> {
+			self.client.hash(number)
+		}
+	}
+
+	impl BlockchainEvents<TestBlock> for TestChain {
+		fn import_notification_stream(&self) -> ImportNotifications<TestBlock> {
+			self.client.import_notification_stream()
+		}
+
+		fn every_import_notification_stream(&self) -> ImportNotifications<TestBlock> {
+			self.client.every_import_notification_stream()
+		}
+
+		fn finality_notification_stream(&self) -> FinalityNotifications<TestBlock> {
+			self.client.finality_notification_stream()
+		}
+
+		fn storage_changes_notification_stream(
+			&self,
+			filter_keys: Option<&[StorageKey]>,
+			child_filter_keys: Option<&[(StorageKey, Option<Vec<StorageKey>>)]>,
+		) -> sp_blockchain::Result<StorageEventStream<H256>> {
+			self.client.storage_changes_notification_stream(filter_keys, child_filter_keys)
+		}
+	}
+
+	/// Services at no address, that never answer.
+	struct SilentServices;
+
+	impl Services for SilentServices {
+		fn resolve<'a>(&'a mut self, authorities: &'a [Authority]) -> BoxFuture<'a, Vec<Node>> {
+			let keys: Vec<PoiId> = authorities.iter().map(|(_, key)| key.clone()).collect();
+			futures::future::ready(network::network_nodes(&keys)).boxed()
+		}
+
+		fn request_signature(
+			&self,
+			_: &Node,
+			_: SignRequest,
+		) -> BoxFuture<'_, Result<H512, PoiError>> {
+			futures::future::pending().boxed()
+		}
+	}
+
+	#[test]
+	fn proving_is_aborted_by_a_new_best_block() {
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let genesis = client.info().genesis_hash;
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let local = SyncCryptoStore::sr25519_generate_new(&*keystore, KEY_TYPE, None).unwrap();
+		let authorities = vec![authority(local), authority(sr25519::Public::from_raw([1; 32]))];
+		let chain = TestChain { client: client.clone(), authorities };
+		// Services are waited for long enough for the tour to outlive the block import.
+		let config = TourConfig { hop_timeout: Duration::from_secs(3600), max_retries: 0 };
+		let mut prover = Prover::new(Arc::new(chain), SilentServices, keystore, config);
+
+		block_on(async {
+			let mut proving = Box::pin(prover.prove(genesis, 0, H256::repeat_byte(2)));
+			assert!(futures::poll!(&mut proving).is_pending());
+
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			client.import(BlockOrigin::Own, block).await.unwrap();
+
+			assert_eq!(proving.await, Err(PoiError::Aborted));
+		});
+	}
+
+	#[test]
+	fn the_initiator_signs_its_own_hops() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let genesis = client.info().genesis_hash;
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let local = SyncCryptoStore::sr25519_generate_new(&*keystore, KEY_TYPE, None).unwrap();
+
+		// Of a network of two nodes, a single service is drawn: pick the other authority so that
+		// the initiator sorts where the draw lands.
+		let signer = KeystoreSigner::new(keystore.clone(), PoiId::from(local));
+		let seed = signer.vrf_seed(&genesis, &genesis, 0).unwrap();
+		let s0 = poi_core::verify_seed(&local, &seed, &genesis, &genesis).unwrap();
+		let drawn = poi_core::select_services(&s0, PoiParameters::default().subset_size, 2);
+		let other = sr25519::Public::from_raw([if drawn == vec![0] { 255 } else { 0 }; 32]);
+
+		let authorities = vec![authority(local), authority(other)];
+		let chain = TestChain { client: client.clone(), authorities };
+		// The other authority never answers, so the tour fails quickly if it is asked.
+		let config = TourConfig { hop_timeout: Duration::from_millis(100), max_retries: 0 };
+		let mut prover = Prover::new(Arc::new(chain), SilentServices, keystore, config);
+
+		let seal = block_on(prover.prove(genesis, 0, H256::repeat_byte(2))).unwrap();
+
+		let keys = vec![PoiId::from(local), PoiId::from(other)];
+		assert!(crate::check_seal(&seal, &genesis, &keys, &PoiParameters::default()));
+	}
+
+	#[test]
+	fn finds_the_local_authority() {
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let local = SyncCryptoStore::sr25519_generate_new(&*keystore, KEY_TYPE, None).unwrap();
+		let other = sr25519::Public::from_raw([1; 32]);
+
+		assert_eq!(
+			local_authority(&keystore, &[authority(other), authority(local)]),
+			Some(PoiId::from(local))
+		);
+		assert_eq!(local_authority(&keystore, &[authority(other)]), None);
+	}
+
+	#[test]
+	fn only_poi_keys_make_the_local_authority() {
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let discovery =
+			SyncCryptoStore::sr25519_generate_new(&*keystore, AUTHORITY_DISCOVERY, None).unwrap();
+
+		assert_eq!(local_authority(&keystore, &[authority(discovery)]), None);
+	}
+
+	#[test]
+	fn keystore_signatures_verify_against_the_authority() {
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let public = SyncCryptoStore::sr25519_generate_new(&*keystore, KEY_TYPE, None).unwrap();
+		let signer = KeystoreSigner::new(keystore, PoiId::from(public));
+
+		let signature = signer.sign_message(b"dependency").unwrap();
+
+		assert_eq!(signer.key(), public);
+		assert!(poi_core::verify_signature(&public, &signature, b"dependency"));
+	}
+
+	#[test]
+	fn keystore_seeds_verify_against_the_authority() {
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let public = SyncCryptoStore::sr25519_generate_new(&*keystore, KEY_TYPE, None).unwrap();
+		let signer = KeystoreSigner::new(keystore, PoiId::from(public));
+		let (genesis_hash, dependency) = (H256::repeat_byte(9), H256::repeat_byte(1));
+
+		let seed = signer.vrf_seed(&genesis_hash, &dependency, 1).unwrap();
+		let again = signer.vrf_seed(&genesis_hash, &dependency, 1).unwrap();
+		let first = signer.vrf_seed(&genesis_hash, &dependency, 0).unwrap();
+
+		let s0 = poi_core::verify_seed(&public, &seed, &genesis_hash, &dependency);
+		assert!(s0.is_some());
+		assert_eq!(s0, poi_core::verify_seed(&public, &again, &genesis_hash, &dependency));
+		assert_ne!(s0, poi_core::verify_seed(&public, &first, &genesis_hash, &dependency));
+	}
+
+	#[test]
+	fn signing_without_the_key_fails() {
+		let keystore: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let signer = KeystoreSigner::new(keystore, PoiId::from(sr25519::Public::from_raw([1; 32])));
+
+		assert_eq!(signer.sign_message(b"dependency"), None);
+		assert_eq!(signer.vrf_seed(&H256::zero(), &H256::zero(), 0), None);
+	}
+}
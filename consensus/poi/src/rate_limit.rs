@@ -0,0 +1,68 @@
+//! Limiting how often each peer may ask this node for a signature.
+
+use parking_lot::Mutex;
+use std::{
+	collections::HashMap,
+	hash::Hash,
+	time::{Duration, Instant},
+};
+
+/// Lets every peer through `limit` times per `period`, counted over fixed windows opened by the
+/// first request of the peer.
+pub struct RateLimiter<K> {
+	limit: u32,
+	period: Duration,
+	windows: Mutex<HashMap<K, (Instant, u32)>>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+	pub fn new(limit: u32, period: Duration) -> Self {
+		Self { limit, period, windows: Mutex::new(HashMap::new()) }
+	}
+
+	/// Count a request from `peer`, returning whether it is within the limit.
+	pub fn check(&self, peer: K) -> bool {
+		self.check_at(peer, Instant::now())
+	}
+
+	fn check_at(&self, peer: K, now: Instant) -> bool {
+		let mut windows = self.windows.lock();
+		let period = self.period;
+		// Peers whose window is over start afresh, so they need not be remembered.
+		windows.retain(|_, (start, _)| now.duration_since(*start) < period);
+
+		let (_, count) = windows.entry(peer).or_insert((now, 0));
+		if *count >= self.limit {
+			return false
+		}
+
+		*count += 1;
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn limits_each_peer_separately() {
+		let limiter = RateLimiter::new(2, Duration::from_secs(60));
+		let now = Instant::now();
+
+		assert!(limiter.check_at(1, now));
+		assert!(limiter.check_at(1, now));
+		assert!(!limiter.check_at(1, now));
+		assert!(limiter.check_at(2, now));
+	}
+
+	#[test]
+	fn limit_is_lifted_once_the_period_is_over() {
+		let limiter = RateLimiter::new(1, Duration::from_secs(60));
+		let now = Instant::now();
+
+		assert!(limiter.check_at(1, now));
+		assert!(!limiter.check_at(1, now + Duration::from_secs(59)));
+		assert!(limiter.check_at(1, now + Duration::from_secs(60)));
+	}
+}
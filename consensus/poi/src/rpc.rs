@@ -0,0 +1,157 @@
+//! `poi_*` RPC methods letting tours reach services over RPC, next to the signing protocol, and
+//! letting external tools check the signatures and proofs they get.
+
+use crate::{
+	check_seal, network, parameters_at,
+	protocol::{ChallengeSigner, ClientView, Refusal, MAX_REQUESTS_PER_PEER, RATE_LIMIT_PERIOD},
+	rate_limit::RateLimiter,
+	Seal,
+};
+use jsonrpsee::{
+	core::{Error as JsonRpseeError, RpcResult},
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use pallet_poi_parameters_runtime_api::PoiParametersApi;
+use pallet_poi_runtime_api::PoiApi as PoiRuntimeApi;
+use parity_scale_codec::Decode;
+use poi_core::{Challenge, PoiId};
+use sc_rpc_api::DenyUnsafe;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, H256, H512};
+use sp_keystore::SyncCryptoStorePtr;
+use sp_runtime::traits::Block as BlockT;
+use std::sync::{
+	atomic::{AtomicUsize, Ordering},
+	Arc,
+};
+
+/// Proofs `poi_verifyProof` checks at once, each check verifying up to twice `MAX_HOPS`
+/// signatures on a blocking thread.
+const MAX_CONCURRENT_PROOF_CHECKS: usize = 4;
+
+#[rpc(client, server)]
+pub trait PoiApi {
+	/// Sign `challenge`, a hop of the tour `initiator` walks, with the PoI key this node holds
+	/// for an authority at the parent block of the challenge.
+	///
+	/// Only challenges made for this chain on top of a known block are signed, for tours of an
+	/// authority at that block, each initiator a limited number of times per minute, as the
+	/// signing protocol does for every peer. The method is unsafe, as it signs with a session key.
+	#[method(name = "poi_signChallenge")]
+	fn sign_challenge(&self, initiator: PoiId, challenge: Challenge) -> RpcResult<H512>;
+
+	/// Whether `signature` is a signature of `message` by the PoI key `public`.
+	#[method(name = "poi_verifySignature")]
+	fn verify_signature(&self, public: PoiId, message: Bytes, signature: H512) -> RpcResult<bool>;
+
+	/// Whether the SCALE-encoded `Seal` `proof` is valid on top of block `at`, checked against
+	/// the authorities and PoI parameters at that block. `at` defaults to the dependency of the
+	/// proof, the block it claims to build on.
+	///
+	/// Only a few proofs are checked at once, calls made meanwhile being refused.
+	#[method(name = "poi_verifyProof", blocking)]
+	fn verify_proof(&self, proof: Bytes, at: Option<H256>) -> RpcResult<bool>;
+}
+
+/// Implements the `poi_*` RPC methods.
+pub struct Poi<B, C> {
+	client: Arc<C>,
+	signer: ChallengeSigner<ClientView<B, C>>,
+	limiter: RateLimiter<PoiId>,
+	proof_checks: AtomicUsize,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<B, C> Poi<B, C>
+where
+	B: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+	C::Api: PoiRuntimeApi<B>,
+{
+	/// Create a new `Poi` instance signing with the PoI keys of `keystore`.
+	pub fn new(client: Arc<C>, keystore: SyncCryptoStorePtr, deny_unsafe: DenyUnsafe) -> Self {
+		let signer = ChallengeSigner::new(ClientView::new(client.clone()), keystore);
+		let limiter = RateLimiter::new(MAX_REQUESTS_PER_PEER, RATE_LIMIT_PERIOD);
+
+		Self { client, signer, limiter, proof_checks: AtomicUsize::new(0), deny_unsafe }
+	}
+
+	fn check_proof(&self, proof: Bytes, at: Option<H256>) -> RpcResult<bool>
+	where
+		C::Api: PoiParametersApi<B>,
+	{
+		let seal = Seal::decode(&mut &proof[..])
+			.map_err(|e| error(INVALID_PROOF, "Proof does not decode as a seal", Some(e)))?;
+		let at = at.unwrap_or(seal.proof.dependency);
+
+		let authorities = network::authorities_at::<B, _>(&*self.client, at).map_err(|e| {
+			error(RUNTIME_ERROR, "Fetching authorities from runtime failed", Some(e))
+		})?;
+		let parameters = parameters_at::<B, _>(&*self.client, at).map_err(|e| {
+			error(RUNTIME_ERROR, "Fetching PoI parameters from runtime failed", Some(e))
+		})?;
+		let keys: Vec<PoiId> = authorities.into_iter().map(|(_, key)| key).collect();
+
+		Ok(check_seal(&seal, &self.client.info().genesis_hash, &keys, &parameters))
+	}
+}
+
+impl<B, C> PoiApiServer for Poi<B, C>
+where
+	B: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: PoiRuntimeApi<B> + PoiParametersApi<B>,
+{
+	fn sign_challenge(&self, initiator: PoiId, challenge: Challenge) -> RpcResult<H512> {
+		self.deny_unsafe.check_if_safe()?;
+
+		if !self.limiter.check(initiator.clone()) {
+			return Err(refused(Refusal::RateLimited))
+		}
+		self.signer.sign(&initiator, None, &challenge).map_err(refused)
+	}
+
+	fn verify_signature(&self, public: PoiId, message: Bytes, signature: H512) -> RpcResult<bool> {
+		Ok(poi_core::verify_signature(public.as_ref(), &signature, &message))
+	}
+
+	fn verify_proof(&self, proof: Bytes, at: Option<H256>) -> RpcResult<bool> {
+		if self.proof_checks.fetch_add(1, Ordering::SeqCst) >= MAX_CONCURRENT_PROOF_CHECKS {
+			self.proof_checks.fetch_sub(1, Ordering::SeqCst);
+			return Err(error(BUSY, "Too many proofs being checked", None::<()>))
+		}
+
+		let result = self.check_proof(proof, at);
+		self.proof_checks.fetch_sub(1, Ordering::SeqCst);
+		result
+	}
+}
+
+/// The challenge is refused, the reason being given as data.
+const REFUSED: i32 = 1;
+/// The initiator is not an authority at the parent block of the challenge.
+const UNKNOWN_INITIATOR: i32 = 2;
+/// The node holds no PoI key of an authority at the parent block of the challenge.
+const NOT_AN_AUTHORITY: i32 = 3;
+/// A runtime API call failed.
+const RUNTIME_ERROR: i32 = 4;
+/// The submitted bytes do not decode as a `Seal`.
+const INVALID_PROOF: i32 = 5;
+/// As many proofs as the node checks at once are being checked already.
+const BUSY: i32 = 6;
+
+fn refused(refusal: Refusal) -> JsonRpseeError {
+	match refusal {
+		Refusal::UnknownInitiator =>
+			error(UNKNOWN_INITIATOR, "Initiator is not an authority", None::<()>),
+		Refusal::UnknownKey => error(NOT_AN_AUTHORITY, "Node is not an authority", None::<()>),
+		refusal => error(REFUSED, "Refused to sign the challenge", Some(refusal)),
+	}
+}
+
+fn error(code: i32, message: &str, data: Option<impl std::fmt::Debug>) -> JsonRpseeError {
+	CallError::Custom(ErrorObject::owned(code, message, data.map(|data| format!("{:?}", data))))
+		.into()
+}
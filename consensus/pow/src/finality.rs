@@ -0,0 +1,109 @@
+//! Depth-based probabilistic finality.
+//!
+//! Proof-of-Work gives no finality by itself, but the chance of a block being reorganised away
+//! drops with every block mined on top of it. [`run_finality`] finalizes blocks once they are
+//! buried under a configured number of confirmations on the best chain, which lets the node
+//! prune old state.
+
+use futures::StreamExt;
+use log::{debug, warn};
+use sc_client_api::{backend::Backend, BlockchainEvents, Finalizer};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, NumberFor, Saturating};
+use std::sync::Arc;
+
+const LOG_TARGET: &str = "pow-finality";
+
+/// Confirmations used by chain specs which do not configure them.
+pub const DEFAULT_CONFIRMATIONS: u32 = 100;
+
+/// The block to finalize when the best block is `best` and `finalized` is already final, if any.
+///
+/// That is the block `confirmations` blocks below the best one, provided it is not final yet.
+pub fn finality_target<N>(best: N, finalized: N, confirmations: u32) -> Option<N>
+where
+	N: Saturating + Ord + From<u32> + Copy,
+{
+	let confirmations = N::from(confirmations);
+	if best < confirmations {
+		return None
+	}
+
+	let target = best.saturating_sub(confirmations);
+	if target > finalized {
+		Some(target)
+	} else {
+		None
+	}
+}
+
+/// Finalize the best chain's blocks as they reach `confirmations` confirmations.
+///
+/// Runs until the client's import notification stream ends.
+pub async fn run_finality<B, BE, C>(client: Arc<C>, confirmations: u32)
+where
+	B: BlockT,
+	BE: Backend<B>,
+	C: BlockchainEvents<B> + HeaderBackend<B> + Finalizer<B, BE>,
+{
+	let mut imports = client.import_notification_stream();
+
+	while let Some(notification) = imports.next().await {
+		if !notification.is_new_best {
+			continue
+		}
+
+		let info = client.info();
+		let target = match finality_target::<NumberFor<B>>(
+			info.best_number,
+			info.finalized_number,
+			confirmations,
+		) {
+			Some(target) => target,
+			None => continue,
+		};
+
+		// The hash at a given number is looked up on the best chain.
+		let hash = match client.hash(target) {
+			Ok(Some(hash)) => hash,
+			Ok(None) => continue,
+			Err(e) => {
+				warn!(target: LOG_TARGET, "Failed to look up block #{}: {}", target, e);
+				continue
+			},
+		};
+
+		match client.finalize_block(hash, None, true) {
+			Ok(()) => debug!(target: LOG_TARGET, "Finalized block #{} ({})", target, hash),
+			Err(e) => warn!(target: LOG_TARGET, "Failed to finalize block #{}: {}", target, e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn nothing_is_finalized_before_enough_confirmations() {
+		assert_eq!(finality_target(0u32, 0, 10), None);
+		assert_eq!(finality_target(9u32, 0, 10), None);
+		assert_eq!(finality_target(10u32, 0, 10), None);
+		assert_eq!(finality_target(11u32, 0, 10), Some(1));
+	}
+
+	#[test]
+	fn finalizes_the_block_confirmations_deep() {
+		assert_eq!(finality_target(150u32, 0, 100), Some(50));
+		assert_eq!(finality_target(150u64, 49, 100), Some(50));
+		assert_eq!(finality_target(150u32, 50, 100), None);
+		// After a reorg to a shorter but heavier chain nothing is un-finalized.
+		assert_eq!(finality_target(140u32, 50, 100), None);
+	}
+
+	#[test]
+	fn zero_confirmations_finalize_the_best_block() {
+		assert_eq!(finality_target(7u32, 6, 0), Some(7));
+		assert_eq!(finality_target(7u32, 7, 0), None);
+	}
+}
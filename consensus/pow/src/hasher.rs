@@ -0,0 +1,142 @@
+//! Hash functions that can back the Proof-of-Work `Compute`/`Seal` pair.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use sp_core::{blake2_256, keccak_256, H256};
+
+/// A hash function used to turn an encoded `Compute` into the work of a `Seal`.
+pub trait PowHasher: Clone + Default + Send + Sync + 'static {
+	/// Hash `data` into a 256-bit digest.
+	fn hash(&self, data: &[u8]) -> H256;
+}
+
+/// SHA3-256, the hash function the chain originally shipped with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Sha3Hasher;
+
+impl PowHasher for Sha3Hasher {
+	fn hash(&self, data: &[u8]) -> H256 {
+		H256::from_slice(Sha3_256::digest(data).as_slice())
+	}
+}
+
+/// Blake2b with a 256-bit output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Blake2bHasher;
+
+impl PowHasher for Blake2bHasher {
+	fn hash(&self, data: &[u8]) -> H256 {
+		H256(blake2_256(data))
+	}
+}
+
+/// Keccak-256, as used by Ethereum (differs from SHA3-256 only in its padding).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct KeccakHasher;
+
+impl PowHasher for KeccakHasher {
+	fn hash(&self, data: &[u8]) -> H256 {
+		H256(keccak_256(data))
+	}
+}
+
+/// Number of 32-byte blocks held in memory by [`MemoryHardHasher`] (512 KiB).
+pub const MEMORY_HARD_BLOCKS: usize = 1 << 14;
+
+/// A memory-hard hash modelled after scrypt's `ROMix`, using Blake2b-256 as the mixing function.
+///
+/// The input is first expanded into [`MEMORY_HARD_BLOCKS`] sequentially dependent blocks, which
+/// are then read back in a data-dependent order. Computing a hash without keeping the whole table
+/// in memory requires recomputing large parts of it for every lookup.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MemoryHardHasher;
+
+impl PowHasher for MemoryHardHasher {
+	fn hash(&self, data: &[u8]) -> H256 {
+		let mut state = blake2_256(data);
+		let mut table = Vec::with_capacity(MEMORY_HARD_BLOCKS);
+
+		for _ in 0..MEMORY_HARD_BLOCKS {
+			table.push(state);
+			state = blake2_256(&state);
+		}
+
+		for _ in 0..MEMORY_HARD_BLOCKS {
+			let mut index = [0u8; 8];
+			index.copy_from_slice(&state[..8]);
+			let block = &table[(u64::from_le_bytes(index) % MEMORY_HARD_BLOCKS as u64) as usize];
+
+			for (byte, mixed) in state.iter_mut().zip(block.iter()) {
+				*byte ^= mixed;
+			}
+			state = blake2_256(&state);
+		}
+
+		H256(state)
+	}
+}
+
+/// The hash functions a chain can be configured with, as recorded in its chain spec.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PowHasherKind {
+	/// See [`Sha3Hasher`].
+	#[default]
+	Sha3,
+	/// See [`Blake2bHasher`].
+	Blake2b,
+	/// See [`KeccakHasher`].
+	Keccak,
+	/// See [`MemoryHardHasher`].
+	MemoryHard,
+}
+
+impl PowHasher for PowHasherKind {
+	fn hash(&self, data: &[u8]) -> H256 {
+		match self {
+			Self::Sha3 => Sha3Hasher.hash(data),
+			Self::Blake2b => Blake2bHasher.hash(data),
+			Self::Keccak => KeccakHasher.hash(data),
+			Self::MemoryHard => MemoryHardHasher.hash(data),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	fn digest(hex: &str) -> H256 {
+		H256::from_str(hex).unwrap()
+	}
+
+	#[test]
+	fn hashers_match_reference_digests() {
+		assert_eq!(
+			Sha3Hasher.hash(b""),
+			digest("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a")
+		);
+		assert_eq!(
+			Blake2bHasher.hash(b""),
+			digest("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8")
+		);
+		assert_eq!(
+			KeccakHasher.hash(b""),
+			digest("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+		);
+		assert_eq!(
+			MemoryHardHasher.hash(b"ambula"),
+			digest("284a9b0e30275b4e8fbca334b3d8d2576502be957386b8b97b57a9fc24b8feba")
+		);
+	}
+
+	#[test]
+	fn kind_dispatches_to_hasher() {
+		let data = b"ambula";
+		assert_eq!(PowHasherKind::Sha3.hash(data), Sha3Hasher.hash(data));
+		assert_eq!(PowHasherKind::Blake2b.hash(data), Blake2bHasher.hash(data));
+		assert_eq!(PowHasherKind::Keccak.hash(data), KeccakHasher.hash(data));
+		assert_eq!(PowHasherKind::MemoryHard.hash(data), MemoryHardHasher.hash(data));
+	}
+}
@@ -1,6 +1,5 @@
 use parity_scale_codec::{Decode, Encode};
 use sc_consensus_pow::{Error, PowAlgorithm};
-use sha3::{Digest, Sha3_256};
 use sp_api::ProvideRuntimeApi;
 use sp_consensus_pow::{DifficultyApi, Seal as RawSeal};
 use sp_core::{H256, U256};
@@ -8,6 +7,19 @@ use sp_runtime::generic::BlockId;
 use sp_runtime::traits::Block as BlockT;
 use std::sync::Arc;
 
+pub mod finality;
+pub mod hasher;
+pub mod miner;
+pub mod rpc;
+pub mod select_chain;
+pub mod stratum;
+
+pub use hasher::{
+	Blake2bHasher, KeccakHasher, MemoryHardHasher, PowHasher, PowHasherKind, Sha3Hasher,
+};
+pub use miner::{Miner, MinerHandle, MiningSource};
+pub use select_chain::HeaviestChain;
+
 /// Determine whether the given hash satisfies the given difficulty.
 /// The test is done by multiplying the two together. If the product
 /// overflows the bounds of U256, then the product (and thus the hash)
@@ -29,18 +41,23 @@ pub struct Seal {
 }
 
 /// A not-yet-computed attempt to solve the proof of work. Calling the
-/// compute method will compute the hash and return the seal.
+/// compute method will hash it with `H` and return the seal.
+///
+/// The hasher is not part of the encoding, so the hashed data is the same
+/// whatever hash function is used.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
-pub struct Compute {
+pub struct Compute<H = Sha3Hasher> {
 	pub difficulty: U256,
 	pub pre_hash: H256,
 	pub nonce: U256,
+	#[codec(skip)]
+	pub hasher: H,
 }
 
-impl Compute {
+impl<H: PowHasher> Compute<H> {
 	pub fn compute(self) -> Seal
 	{
-		let work = H256::from_slice(Sha3_256::digest(&self.encode()[..]).as_slice());
+		let work = self.hasher.hash(&self.encode()[..]);
 
 		Seal {
 			nonce: self.nonce,
@@ -103,6 +120,7 @@ impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for MinimalSha3Algorithm<C> {
 			difficulty,
 			pre_hash: *pre_hash,
 			nonce: seal.nonce,
+			hasher: Sha3Hasher,
 		};
 
 		if compute.compute() != seal {
@@ -113,28 +131,30 @@ impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for MinimalSha3Algorithm<C> {
 	}
 }
 
-/// A complete PoW Algorithm that uses Sha3 hashing.
-/// Needs a reference to the client so it can grab the difficulty from the runtime.
-pub struct Sha3Algorithm<C> {
+/// A complete PoW Algorithm, Sha3 hashing by default.
+/// Needs a reference to the client so it can grab the difficulty from the runtime,
+/// and the hash function `H` the chain was configured with.
+pub struct Sha3Algorithm<C, H = Sha3Hasher> {
 	client: Arc<C>,
+	hasher: H,
 }
 
-impl<C> Sha3Algorithm<C> {
-	pub fn new(client: Arc<C>) -> Self {
-		Self { client }
+impl<C, H> Sha3Algorithm<C, H> {
+	pub fn new(client: Arc<C>, hasher: H) -> Self {
+		Self { client, hasher }
 	}
 }
 
 // Manually implement clone. Deriving doesn't work because
 // it'll derive impl<C: Clone> Clone for Sha3Algorithm<C>. But C in practice isn't Clone.
-impl<C> Clone for Sha3Algorithm<C> {
+impl<C, H: Clone> Clone for Sha3Algorithm<C, H> {
 	fn clone(&self) -> Self {
-		Self::new(self.client.clone())
+		Self::new(self.client.clone(), self.hasher.clone())
 	}
 }
 
 // Here we implement the general PowAlgorithm trait for our concrete Sha3Algorithm
-impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for Sha3Algorithm<C>
+impl<B: BlockT<Hash = H256>, C, H: PowHasher> PowAlgorithm<B> for Sha3Algorithm<C, H>
 where
 	C: ProvideRuntimeApi<B>,
 	C::Api: DifficultyApi<B, U256>,
@@ -177,6 +197,7 @@ where
 			difficulty,
 			pre_hash: *pre_hash,
 			nonce: seal.nonce,
+			hasher: self.hasher.clone(),
 		};
 
 		if compute.compute() != seal {
@@ -0,0 +1,348 @@
+//! A native, multi-threaded miner driving a `sc_consensus_pow::MiningHandle`.
+//!
+//! Every worker thread owns a disjoint slice of the nonce space and keeps hashing it until
+//! either a seal meeting the difficulty is found or the mining metadata changes (new best block
+//! or new block template), in which case it restarts from the beginning of its slice. A worker
+//! whose seal is rejected fetches the template again before going on. Workers run until the
+//! [`MinerHandle`] returned by [`Miner::start`] is dropped.
+
+use crate::{hash_meets_difficulty, Compute, PowHasher};
+use futures::executor::block_on;
+use log::{debug, info, warn};
+use parity_scale_codec::Encode;
+use sc_consensus::JustificationSyncLink;
+use sc_consensus_pow::{MiningHandle, MiningMetadata, PowAlgorithm};
+use sp_api::ProvideRuntimeApi;
+use sp_consensus_pow::Seal as RawSeal;
+use sp_core::{H256, U256};
+use sp_runtime::traits::Block as BlockT;
+use std::{
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+
+const LOG_TARGET: &str = "pow-miner";
+
+/// Number of nonces a worker tries before checking whether the mining metadata changed.
+const BATCH_SIZE: u64 = 10_000;
+
+/// How long an idle worker waits before polling for new mining metadata.
+const IDLE_DELAY: Duration = Duration::from_millis(250);
+
+/// Something that hands out work to miners and accepts their solutions.
+///
+/// This is implemented for `sc_consensus_pow::MiningHandle`, the abstraction mainly exists so
+/// the mining loop does not have to spell out the handle's generic parameters.
+pub trait MiningSource: Clone + Send + 'static {
+	/// A counter that changes every time the block template changes.
+	fn version(&self) -> usize;
+
+	/// The work currently being mined on, if any.
+	fn metadata(&self) -> Option<MiningMetadata<H256, U256>>;
+
+	/// Submit a seal for the current work, returning whether it was imported.
+	fn submit(&self, seal: RawSeal) -> bool;
+}
+
+impl<B, A, C, L, Proof> MiningSource for MiningHandle<B, A, C, L, Proof>
+where
+	B: BlockT<Hash = H256>,
+	A: PowAlgorithm<B, Difficulty = U256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + 'static,
+	L: JustificationSyncLink<B> + 'static,
+	Proof: Send + 'static,
+	sp_api::TransactionFor<C, B>: Send + 'static,
+{
+	fn version(&self) -> usize {
+		MiningHandle::version(self)
+	}
+
+	fn metadata(&self) -> Option<MiningMetadata<H256, U256>> {
+		MiningHandle::metadata(self)
+	}
+
+	fn submit(&self, seal: RawSeal) -> bool {
+		block_on(MiningHandle::submit(self, seal))
+	}
+}
+
+/// The range of nonces `[start, end)` assigned to worker `index` out of `threads` workers.
+///
+/// The last worker's range is open-ended and covers the remainder of the nonce space.
+pub fn nonce_range(index: usize, threads: usize) -> (U256, Option<U256>) {
+	let threads = threads.max(1);
+	let span = U256::MAX / U256::from(threads);
+	let start = span * U256::from(index);
+
+	if index + 1 >= threads {
+		(start, None)
+	} else {
+		(start, Some(start + span))
+	}
+}
+
+/// A pool of mining threads hashing with `H` the metadata provided by a [`MiningSource`].
+pub struct Miner<S, H> {
+	source: S,
+	threads: usize,
+	hasher: H,
+}
+
+impl<S: MiningSource, H: PowHasher> Miner<S, H> {
+	/// Create a miner using `threads` worker threads. At least one thread is always used.
+	pub fn new(source: S, threads: usize, hasher: H) -> Self {
+		Self { source, threads: threads.max(1), hasher }
+	}
+
+	/// Spawn the worker threads. They run until the returned handle is dropped.
+	pub fn start(self) -> std::io::Result<MinerHandle> {
+		info!(target: LOG_TARGET, "⛏  Starting {} mining thread(s)", self.threads);
+
+		let mut handle =
+			MinerHandle { stop: Arc::new(AtomicBool::new(false)), workers: Vec::new() };
+		for index in 0..self.threads {
+			let source = self.source.clone();
+			let hasher = self.hasher.clone();
+			let threads = self.threads;
+			let stop = handle.stop.clone();
+			// Workers spawned so far are stopped along with the handle if this one fails.
+			handle.workers.push(
+				thread::Builder::new()
+					.name(format!("pow-miner-{}", index))
+					.spawn(move || mine(source, hasher, index, threads, &stop))?,
+			);
+		}
+
+		Ok(handle)
+	}
+}
+
+/// Keeps the worker threads of a [`Miner`] running. Dropping it stops them and waits for them to
+/// finish their current batch.
+pub struct MinerHandle {
+	stop: Arc<AtomicBool>,
+	workers: Vec<JoinHandle<()>>,
+}
+
+impl Drop for MinerHandle {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::SeqCst);
+		for worker in self.workers.drain(..) {
+			if worker.join().is_err() {
+				warn!(target: LOG_TARGET, "A mining thread panicked");
+			}
+		}
+	}
+}
+
+/// The nonce following `nonce` in the range `[start, end)`, wrapping around to `start`.
+fn next_nonce(nonce: U256, start: U256, end: Option<U256>) -> U256 {
+	match end {
+		Some(end) if nonce + 1 >= end => start,
+		_ if nonce == U256::MAX => start,
+		_ => nonce + 1,
+	}
+}
+
+/// The mining loop run by a single worker thread, until `stop` is set.
+fn mine<S: MiningSource, H: PowHasher>(
+	source: S,
+	hasher: H,
+	index: usize,
+	threads: usize,
+	stop: &AtomicBool,
+) {
+	let (start, end) = nonce_range(index, threads);
+	// The pre-hash a seal was rejected for, and the nonce to resume from if it is still current.
+	let mut rejected: Option<(H256, U256)> = None;
+
+	'template: while !stop.load(Ordering::Relaxed) {
+		let version = source.version();
+		let metadata = match source.metadata() {
+			Some(metadata) => metadata,
+			None => {
+				thread::sleep(IDLE_DELAY);
+				continue;
+			},
+		};
+
+		let mut nonce = match rejected.take() {
+			Some((pre_hash, resume)) if pre_hash == metadata.pre_hash => resume,
+			_ => start,
+		};
+		while source.version() == version && !stop.load(Ordering::Relaxed) {
+			for _ in 0..BATCH_SIZE {
+				let compute = Compute {
+					difficulty: metadata.difficulty,
+					pre_hash: metadata.pre_hash,
+					nonce,
+					hasher: hasher.clone(),
+				};
+				let seal = compute.compute();
+
+				if hash_meets_difficulty(&seal.work, seal.difficulty) {
+					debug!(target: LOG_TARGET, "Worker {} found a seal with nonce {}", index, nonce);
+					if source.submit(seal.encode()) {
+						break;
+					}
+					warn!(target: LOG_TARGET, "Seal found by worker {} was rejected", index);
+
+					// The template may have gone stale without its version changing.
+					rejected = Some((metadata.pre_hash, next_nonce(nonce, start, end)));
+					continue 'template;
+				}
+
+				nonce = next_nonce(nonce, start, end);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parity_scale_codec::Decode;
+	use std::{
+		sync::{atomic::AtomicUsize, Mutex},
+		time::Instant,
+	};
+
+	#[derive(Clone, Default)]
+	struct TestSource {
+		version: Arc<AtomicUsize>,
+		metadata: Arc<Mutex<Option<MiningMetadata<H256, U256>>>>,
+		submitted: Arc<Mutex<Vec<RawSeal>>>,
+		/// Template the first submission is rejected for, without the version changing.
+		replacement: Arc<Mutex<Option<MiningMetadata<H256, U256>>>>,
+	}
+
+	impl MiningSource for TestSource {
+		fn version(&self) -> usize {
+			self.version.load(Ordering::SeqCst)
+		}
+
+		fn metadata(&self) -> Option<MiningMetadata<H256, U256>> {
+			self.metadata.lock().unwrap().clone()
+		}
+
+		fn submit(&self, seal: RawSeal) -> bool {
+			if let Some(replacement) = self.replacement.lock().unwrap().take() {
+				*self.metadata.lock().unwrap() = Some(replacement);
+				return false;
+			}
+			if self.metadata.lock().unwrap().take().is_none() {
+				return false;
+			}
+			self.submitted.lock().unwrap().push(seal);
+			self.version.fetch_add(1, Ordering::SeqCst);
+			true
+		}
+	}
+
+	#[test]
+	fn nonce_ranges_are_disjoint_and_cover_the_space() {
+		let threads = 3;
+		let ranges: Vec<_> = (0..threads).map(|i| nonce_range(i, threads)).collect();
+
+		assert_eq!(ranges[0].0, U256::zero());
+		assert_eq!(ranges[0].1, Some(ranges[1].0));
+		assert_eq!(ranges[1].1, Some(ranges[2].0));
+		assert_eq!(ranges[2].1, None);
+		assert_eq!(nonce_range(0, 1), (U256::zero(), None));
+	}
+
+	#[test]
+	fn miner_submits_a_valid_seal() {
+		let source = TestSource::default();
+		let pre_hash = H256::repeat_byte(7);
+		*source.metadata.lock().unwrap() = Some(MiningMetadata {
+			best_hash: H256::zero(),
+			pre_hash,
+			pre_runtime: None,
+			difficulty: U256::from(16),
+		});
+
+		let miner = Miner::new(source.clone(), 2, crate::Blake2bHasher).start().unwrap();
+
+		let deadline = Instant::now() + Duration::from_secs(30);
+		while source.submitted.lock().unwrap().is_empty() {
+			assert!(Instant::now() < deadline, "miner did not find a seal in time");
+			thread::sleep(Duration::from_millis(10));
+		}
+		drop(miner);
+
+		let submitted = source.submitted.lock().unwrap();
+		assert_eq!(submitted.len(), 1);
+		let seal = crate::Seal::decode(&mut &submitted[0][..]).unwrap();
+		assert!(hash_meets_difficulty(&seal.work, seal.difficulty));
+		assert_eq!(
+			Compute {
+				difficulty: seal.difficulty,
+				pre_hash,
+				nonce: seal.nonce,
+				hasher: crate::Blake2bHasher
+			}
+			.compute(),
+			seal
+		);
+	}
+
+	#[test]
+	fn dropping_the_handle_stops_the_workers() {
+		let source = TestSource::default();
+		// A difficulty no seal meets, so that workers keep hashing
+		*source.metadata.lock().unwrap() = Some(MiningMetadata {
+			best_hash: H256::zero(),
+			pre_hash: H256::repeat_byte(7),
+			pre_runtime: None,
+			difficulty: U256::MAX,
+		});
+
+		let miner = Miner::new(source.clone(), 2, crate::Blake2bHasher).start().unwrap();
+		assert_eq!(Arc::strong_count(&source.version), 3);
+		drop(miner);
+
+		// The workers are joined, and the sources they held dropped
+		assert_eq!(Arc::strong_count(&source.version), 1);
+		assert!(source.submitted.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn miner_fetches_the_template_again_after_a_rejection() {
+		let source = TestSource::default();
+		let metadata = |pre_hash| MiningMetadata {
+			best_hash: H256::zero(),
+			pre_hash,
+			pre_runtime: None,
+			difficulty: U256::from(16),
+		};
+		*source.metadata.lock().unwrap() = Some(metadata(H256::repeat_byte(7)));
+		*source.replacement.lock().unwrap() = Some(metadata(H256::repeat_byte(8)));
+
+		let miner = Miner::new(source.clone(), 1, crate::Blake2bHasher).start().unwrap();
+
+		let deadline = Instant::now() + Duration::from_secs(30);
+		while source.submitted.lock().unwrap().is_empty() {
+			assert!(Instant::now() < deadline, "miner did not find a seal in time");
+			thread::sleep(Duration::from_millis(10));
+		}
+		drop(miner);
+
+		let submitted = source.submitted.lock().unwrap();
+		let seal = crate::Seal::decode(&mut &submitted[0][..]).unwrap();
+		assert_eq!(
+			Compute {
+				difficulty: seal.difficulty,
+				pre_hash: H256::repeat_byte(8),
+				nonce: seal.nonce,
+				hasher: crate::Blake2bHasher
+			}
+			.compute(),
+			seal
+		);
+	}
+}
@@ -0,0 +1,118 @@
+//! `mining_*` RPC methods letting external processes mine on the node's block template, and
+//! `finality_*` methods describing the depth-based finality of the chain.
+
+use crate::{MiningSource, Seal};
+use jsonrpsee::{
+	core::{Error as JsonRpseeError, RpcResult},
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use parity_scale_codec::Decode;
+use sc_rpc_api::DenyUnsafe;
+use serde::{Deserialize, Serialize};
+use sp_core::{Bytes, H256, U256};
+
+/// The work an external miner has to solve.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Work {
+	/// Hash of the block the template builds on.
+	pub best_hash: H256,
+	/// Pre-hash of the block template, to be sealed.
+	pub pre_hash: H256,
+	/// Difficulty the seal must meet.
+	pub difficulty: U256,
+	/// Template version, changes every time the template is replaced.
+	pub version: u64,
+}
+
+#[rpc(client, server)]
+pub trait MiningApi {
+	/// Get the work currently being mined on, or `None` if no block template is ready yet.
+	#[method(name = "mining_getWork")]
+	fn get_work(&self) -> RpcResult<Option<Work>>;
+
+	/// Submit a SCALE-encoded `Seal` for the current work. Returns whether the block was
+	/// accepted and imported. The method is unsafe, as it imports blocks.
+	#[method(name = "mining_submitWork", blocking)]
+	fn submit_work(&self, seal: Bytes) -> RpcResult<bool>;
+}
+
+/// Implements the `mining_*` RPC methods on top of a [`MiningSource`].
+pub struct Mining<S> {
+	source: Option<S>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<S> Mining<S> {
+	/// Create a new `Mining` instance. `source` is `None` on nodes that do not author blocks.
+	pub fn new(source: Option<S>, deny_unsafe: DenyUnsafe) -> Self {
+		Self { source, deny_unsafe }
+	}
+
+	fn source(&self) -> RpcResult<&S> {
+		self.source
+			.as_ref()
+			.ok_or_else(|| error(NOT_MINING, "Node is not mining", None::<()>))
+	}
+}
+
+impl<S: MiningSource + Sync> MiningApiServer for Mining<S> {
+	fn get_work(&self) -> RpcResult<Option<Work>> {
+		let source = self.source()?;
+		let version = source.version() as u64;
+
+		Ok(source.metadata().map(|metadata| Work {
+			best_hash: metadata.best_hash,
+			pre_hash: metadata.pre_hash,
+			difficulty: metadata.difficulty,
+			version,
+		}))
+	}
+
+	fn submit_work(&self, seal: Bytes) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let source = self.source()?;
+
+		if let Err(e) = Seal::decode(&mut &seal[..]) {
+			return Err(error(INVALID_SEAL, "Invalid seal", Some(e)))
+		}
+
+		Ok(source.submit(seal.0))
+	}
+}
+
+#[rpc(client, server)]
+pub trait FinalityApi {
+	/// Number of blocks that must be mined on top of a block before it is finalized.
+	#[method(name = "finality_confirmations")]
+	fn confirmations(&self) -> RpcResult<u32>;
+}
+
+/// Implements the `finality_*` RPC methods.
+pub struct Finality {
+	confirmations: u32,
+}
+
+impl Finality {
+	/// Create a new `Finality` instance for a chain finalizing at `confirmations` blocks deep.
+	pub fn new(confirmations: u32) -> Self {
+		Self { confirmations }
+	}
+}
+
+impl FinalityApiServer for Finality {
+	fn confirmations(&self) -> RpcResult<u32> {
+		Ok(self.confirmations)
+	}
+}
+
+/// The node does not author blocks, so it has no work to hand out.
+const NOT_MINING: i32 = 1;
+/// The submitted bytes do not decode as a `Seal`.
+const INVALID_SEAL: i32 = 2;
+
+fn error(code: i32, message: &str, data: Option<impl std::fmt::Debug>) -> JsonRpseeError {
+	CallError::Custom(ErrorObject::owned(code, message, data.map(|data| format!("{:?}", data))))
+		.into()
+}
@@ -0,0 +1,261 @@
+//! Fork choice following the chain with the most accumulated work.
+//!
+//! `PowBlockImport` records the total difficulty of every block it imports as `PowAux`
+//! auxiliary data. [`HeaviestChain`] reads it back for every leaf and selects the heaviest one,
+//! unlike `sc_consensus::LongestChain` which only looks at block numbers.
+
+use async_trait::async_trait;
+use sc_client_api::backend::Backend;
+use sc_consensus_pow::PowAux;
+use sp_blockchain::{Backend as _, HeaderBackend};
+use sp_consensus::{Error as ConsensusError, SelectChain};
+use sp_core::U256;
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc};
+
+/// Pick the heaviest of `candidates`, given as `(hash, total difficulty)` pairs.
+///
+/// On a tie `current_best` is kept if it is among the heaviest candidates, so that the node does
+/// not switch between equally heavy forks. Otherwise the first heaviest candidate wins.
+pub fn heaviest<H, D>(candidates: impl IntoIterator<Item = (H, D)>, current_best: &H) -> Option<H>
+where
+	H: PartialEq,
+	D: Ord,
+{
+	let mut best: Option<(H, D)> = None;
+
+	for (hash, difficulty) in candidates {
+		let replace = match &best {
+			None => true,
+			Some((_, best_difficulty)) =>
+				difficulty > *best_difficulty ||
+					(difficulty == *best_difficulty && hash == *current_best),
+		};
+
+		if replace {
+			best = Some((hash, difficulty));
+		}
+	}
+
+	best.map(|(hash, _)| hash)
+}
+
+/// A `SelectChain` returning the leaf with the highest total difficulty as best chain.
+pub struct HeaviestChain<B, Block> {
+	backend: Arc<B>,
+	_phantom: PhantomData<Block>,
+}
+
+impl<B, Block> Clone for HeaviestChain<B, Block> {
+	fn clone(&self) -> Self {
+		Self { backend: self.backend.clone(), _phantom: PhantomData }
+	}
+}
+
+impl<B, Block> HeaviestChain<B, Block>
+where
+	B: Backend<Block>,
+	Block: BlockT,
+{
+	/// Instantiate a new `HeaviestChain` for `backend`.
+	pub fn new(backend: Arc<B>) -> Self {
+		Self { backend, _phantom: PhantomData }
+	}
+
+	/// The total difficulty of the chain ending at `hash`, zero for blocks without `PowAux`.
+	pub fn total_difficulty(&self, hash: &Block::Hash) -> Result<U256, ConsensusError> {
+		PowAux::<U256>::read::<_, Block>(&*self.backend, hash)
+			.map(|aux| aux.total_difficulty)
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))
+	}
+
+	fn best_hash(&self) -> Result<Block::Hash, ConsensusError> {
+		let blockchain = self.backend.blockchain();
+		let current_best = blockchain.info().best_hash;
+
+		let candidates = blockchain
+			.leaves()
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.into_iter()
+			.map(|hash| Ok((hash, self.total_difficulty(&hash)?)))
+			.collect::<Result<Vec<_>, ConsensusError>>()?;
+
+		Ok(heaviest(candidates, &current_best).unwrap_or(current_best))
+	}
+}
+
+#[async_trait]
+impl<B, Block> SelectChain<Block> for HeaviestChain<B, Block>
+where
+	B: Backend<Block>,
+	Block: BlockT,
+{
+	async fn leaves(&self) -> Result<Vec<Block::Hash>, ConsensusError> {
+		self.backend
+			.blockchain()
+			.leaves()
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))
+	}
+
+	async fn best_chain(&self) -> Result<Block::Header, ConsensusError> {
+		let best_hash = self.best_hash()?;
+
+		self.backend
+			.blockchain()
+			.header(best_hash)
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.ok_or_else(|| {
+				ConsensusError::ChainLookup(format!("Missing header for {:?}", best_hash))
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use parity_scale_codec::Encode;
+	use sc_block_builder::BlockBuilderProvider;
+	use sc_client_api::AuxStore;
+	use sp_consensus::BlockOrigin;
+	use sp_core::H256;
+	use sp_runtime::traits::Header as _;
+	use substrate_test_runtime_client::{
+		runtime::Block as TestBlock, BlockBuilderExt, ClientBlockImportExt,
+		DefaultTestClientBuilderExt, TestClient, TestClientBuilder, TestClientBuilderExt,
+	};
+
+	/// Import a block of `difficulty` on top of `parent`, recording its `PowAux` the way
+	/// `PowBlockImport` does. Blocks of different forks are told apart by `fork`.
+	fn import(client: &mut TestClient, parent: H256, fork: u8, difficulty: u64) -> H256 {
+		let mut builder = client.new_block_at(parent, Default::default(), false).unwrap();
+		builder.push_storage_change(b"fork".to_vec(), Some(vec![fork])).unwrap();
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+		let parent = PowAux::<U256>::read::<_, TestBlock>(&*client, &parent).unwrap();
+		let aux = PowAux {
+			difficulty: U256::from(difficulty),
+			total_difficulty: parent.total_difficulty + difficulty,
+		};
+		// The key `sc_consensus_pow` stores `PowAux` under.
+		let key = [&b"PoW:"[..], hash.as_ref()].concat();
+		client.insert_aux(&[(&key[..], &aux.encode()[..])], &[]).unwrap();
+
+		hash
+	}
+
+	#[test]
+	fn best_chain_is_the_heaviest_fork_of_the_backend() {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let mut client = builder.build();
+		let chain = HeaviestChain::<_, TestBlock>::new(backend);
+		let genesis = client.chain_info().genesis_hash;
+
+		let long = (0..3).fold(genesis, |parent, _| import(&mut client, parent, 1, 10));
+		let heavy = import(&mut client, genesis, 2, 40);
+
+		// The client itself follows the longest chain, `HeaviestChain` the heaviest one.
+		assert_eq!(client.chain_info().best_hash, long);
+		assert_eq!(chain.total_difficulty(&long).unwrap(), U256::from(30));
+		assert_eq!(chain.total_difficulty(&heavy).unwrap(), U256::from(40));
+		assert_eq!(block_on(chain.best_chain()).unwrap().hash(), heavy);
+
+		let long = import(&mut client, long, 1, 20);
+		assert_eq!(block_on(chain.best_chain()).unwrap().hash(), long);
+	}
+
+	/// A block tree recording total difficulties the way `PowBlockImport` does, and following
+	/// the heaviest leaf as a node would.
+	#[derive(Default)]
+	struct TestTree {
+		/// `(parent, total difficulty)` of every block, indexed by block.
+		blocks: Vec<(usize, u64)>,
+		leaves: Vec<usize>,
+		best: usize,
+	}
+
+	impl TestTree {
+		fn new() -> Self {
+			Self { blocks: vec![(0, 0)], leaves: vec![0], best: 0 }
+		}
+
+		/// Import a block on top of `parent`, returning it and the new best block.
+		fn import(&mut self, parent: usize, difficulty: u64) -> (usize, usize) {
+			let block = self.blocks.len();
+			self.blocks.push((parent, self.blocks[parent].1 + difficulty));
+			self.leaves.retain(|leaf| *leaf != parent);
+			self.leaves.push(block);
+
+			let candidates = self.leaves.iter().map(|leaf| (*leaf, self.blocks[*leaf].1));
+			self.best = heaviest(candidates, &self.best).unwrap();
+			(block, self.best)
+		}
+
+		/// Import a chain of blocks with the given difficulties on top of `parent`.
+		fn extend(&mut self, parent: usize, difficulties: &[u64]) -> usize {
+			difficulties
+				.iter()
+				.fold(parent, |parent, difficulty| self.import(parent, *difficulty).0)
+		}
+	}
+
+	#[test]
+	fn heaviest_ignores_chain_length() {
+		let mut tree = TestTree::new();
+		let long = tree.extend(0, &[10, 10, 10]);
+		let heavy = tree.extend(0, &[40]);
+
+		assert_eq!(tree.best, heavy);
+		assert_ne!(tree.best, long);
+	}
+
+	#[test]
+	fn reorgs_to_a_fork_once_it_is_heavier() {
+		let mut tree = TestTree::new();
+		let a = tree.extend(0, &[100, 100]);
+		assert_eq!(tree.best, a);
+
+		// A fork starting at the first block catches up without overtaking.
+		let b = tree.extend(1, &[50, 50]);
+		assert_eq!(tree.best, a);
+
+		// One more block makes the fork heavier, the node reorgs to it.
+		let (b, best) = tree.import(b, 10);
+		assert_eq!(best, b);
+
+		// And back once the original chain grows heavier again.
+		let (a, best) = tree.import(a, 20);
+		assert_eq!(best, a);
+	}
+
+	#[test]
+	fn lighter_blocks_do_not_cause_a_reorg() {
+		let mut tree = TestTree::new();
+		let heavy = tree.extend(0, &[1000]);
+
+		for _ in 0..5 {
+			let (_, best) = tree.import(0, 100);
+			assert_eq!(best, heavy);
+		}
+
+		let light = tree.extend(0, &[100, 100, 100, 100, 100, 100, 100, 100, 100]);
+		assert_eq!(tree.best, heavy);
+		let (_, best) = tree.import(light, 101);
+		assert_ne!(best, heavy);
+	}
+
+	#[test]
+	fn ties_keep_the_current_best() {
+		let mut tree = TestTree::new();
+		let first = tree.extend(0, &[10, 10]);
+		let (_, best) = tree.import(0, 20);
+		assert_eq!(best, first);
+
+		assert_eq!(heaviest(vec![(1, 5), (2, 5)], &2), Some(2));
+		assert_eq!(heaviest(vec![(1, 5), (2, 5)], &3), Some(1));
+		assert_eq!(heaviest(Vec::<(u32, u32)>::new(), &3), None);
+	}
+}
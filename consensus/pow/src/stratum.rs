@@ -0,0 +1,642 @@
+//! A lightweight, Stratum-like mining protocol server.
+//!
+//! Miners connect over TCP and exchange line-delimited JSON messages with the node:
+//!
+//! - `{"id": 1, "method": "mining.subscribe"}` registers the connection for jobs. The result holds
+//!   the connection's `extranonce`: the upper 64 bits of every nonce it submits must be equal to
+//!   it, which splits the nonce space between connections.
+//! - `{"id": null, "method": "mining.notify", "params": {..}}` is pushed to subscribed miners every
+//!   time the node's block template changes.
+//! - `{"id": 2, "method": "mining.submit", "params": [jobId, nonce]}` submits a share. Shares are
+//!   checked against the lower `shareDifficulty`; those that also meet the block difficulty are
+//!   submitted to the node as a real seal. A share can only be submitted once per job.
+//!
+//! Connections sending a line longer than [`MAX_LINE_LENGTH`] bytes are closed.
+
+use crate::{hash_meets_difficulty, Compute, MiningSource, PowHasher, Seal};
+use log::{debug, info, warn};
+use parity_scale_codec::Encode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sp_core::{H256, U256};
+use std::{
+	collections::HashSet,
+	io,
+	net::SocketAddr,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+use tokio::{
+	io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{tcp::OwnedWriteHalf, TcpListener, TcpStream},
+	sync::watch,
+};
+
+const LOG_TARGET: &str = "stratum";
+
+/// How often the block template is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of low nonce bits left to a connection, the rest being its extranonce.
+const EXTRANONCE_SHIFT: usize = 192;
+
+/// Longest line, in bytes and without its line feed, a miner may send.
+pub const MAX_LINE_LENGTH: usize = 4096;
+
+/// Stratum server configuration.
+#[derive(Clone, Debug)]
+pub struct StratumConfig {
+	/// Address the server listens on.
+	pub listen_addr: SocketAddr,
+	/// Shares must meet the block difficulty divided by this value.
+	pub share_divisor: u64,
+}
+
+/// A job handed out to miners, derived from the node's mining metadata.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Job {
+	/// Identifies the job, this is the version of the block template.
+	pub id: u64,
+	/// Pre-hash of the block template.
+	pub pre_hash: H256,
+	/// Difficulty a seal needs to meet to be a valid block.
+	pub difficulty: U256,
+}
+
+/// Why a share was refused.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShareError {
+	/// The share was computed for a job that is no longer current.
+	StaleJob,
+	/// The nonce lies outside of the connection's extranonce range.
+	ForeignNonce,
+	/// The work does not meet the share difficulty.
+	LowDifficulty,
+	/// The share was already submitted for this job.
+	Duplicate,
+}
+
+impl ShareError {
+	fn message(&self) -> &'static str {
+		match self {
+			Self::StaleJob => "Stale job",
+			Self::ForeignNonce => "Nonce outside of the connection's extranonce range",
+			Self::LowDifficulty => "Share does not meet the share difficulty",
+			Self::Duplicate => "Duplicate share",
+		}
+	}
+}
+
+/// The difficulty shares of a job with `difficulty` are checked against.
+pub fn share_difficulty(difficulty: U256, share_divisor: u64) -> U256 {
+	(difficulty / U256::from(share_divisor.max(1))).max(U256::one())
+}
+
+/// Check a share submitted by the connection owning `extranonce`.
+///
+/// Returns the seal when the share also meets the block difficulty and should be submitted
+/// as a block, `None` when it is only a valid share.
+pub fn check_share<H: PowHasher>(
+	job: &Job,
+	extranonce: u64,
+	nonce: U256,
+	share_divisor: u64,
+	hasher: H,
+) -> Result<Option<Seal>, ShareError> {
+	if (nonce >> EXTRANONCE_SHIFT) != U256::from(extranonce) {
+		return Err(ShareError::ForeignNonce)
+	}
+
+	let seal =
+		Compute { difficulty: job.difficulty, pre_hash: job.pre_hash, nonce, hasher }.compute();
+
+	if !hash_meets_difficulty(&seal.work, share_difficulty(job.difficulty, share_divisor)) {
+		return Err(ShareError::LowDifficulty)
+	}
+
+	if hash_meets_difficulty(&seal.work, job.difficulty) {
+		Ok(Some(seal))
+	} else {
+		Ok(None)
+	}
+}
+
+#[derive(Deserialize)]
+struct Request {
+	#[serde(default)]
+	id: Value,
+	method: String,
+	#[serde(default)]
+	params: Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Notify {
+	job_id: u64,
+	pre_hash: H256,
+	difficulty: U256,
+	share_difficulty: U256,
+	extranonce: u64,
+}
+
+/// A bound Stratum server, ready to be run.
+pub struct StratumServer<S, H> {
+	listener: std::net::TcpListener,
+	config: StratumConfig,
+	source: S,
+	hasher: H,
+}
+
+impl<S: MiningSource + Sync, H: PowHasher> StratumServer<S, H> {
+	/// Bind the server to `config.listen_addr`.
+	pub fn bind(config: StratumConfig, source: S, hasher: H) -> std::io::Result<Self> {
+		let listener = std::net::TcpListener::bind(config.listen_addr)?;
+		listener.set_nonblocking(true)?;
+
+		Ok(Self { listener, config, source, hasher })
+	}
+
+	/// The address the server is listening on.
+	pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+		self.listener.local_addr()
+	}
+
+	/// Accept miners and serve them jobs until the future is dropped.
+	pub async fn run(self) {
+		let listener = match TcpListener::from_std(self.listener) {
+			Ok(listener) => listener,
+			Err(e) => return warn!(target: LOG_TARGET, "Failed to start Stratum server: {}", e),
+		};
+		info!(target: LOG_TARGET, "⛏  Stratum server listening on {}", self.config.listen_addr);
+
+		let (jobs, receiver) = watch::channel(None);
+		tokio::spawn(poll_jobs(self.source.clone(), jobs));
+
+		let shared = Arc::new(Shared {
+			source: self.source,
+			hasher: self.hasher,
+			share_divisor: self.config.share_divisor,
+			jobs: receiver,
+		});
+		let next_extranonce = AtomicU64::new(1);
+
+		loop {
+			match listener.accept().await {
+				Ok((stream, peer)) => {
+					let extranonce = next_extranonce.fetch_add(1, Ordering::Relaxed);
+					debug!(target: LOG_TARGET, "Miner {} connected, extranonce {}", peer, extranonce);
+					tokio::spawn(Connection::new(shared.clone(), extranonce).run(stream));
+				},
+				Err(e) => warn!(target: LOG_TARGET, "Failed to accept connection: {}", e),
+			}
+		}
+	}
+}
+
+/// State shared by all connections.
+struct Shared<S, H> {
+	source: S,
+	hasher: H,
+	share_divisor: u64,
+	jobs: watch::Receiver<Option<Job>>,
+}
+
+/// Publish a new job every time the block template changes.
+async fn poll_jobs<S: MiningSource>(source: S, jobs: watch::Sender<Option<Job>>) {
+	let mut version = None;
+
+	loop {
+		let current = source.version();
+		if version != Some(current) {
+			version = Some(current);
+			jobs.send_replace(source.metadata().map(|metadata| Job {
+				id: current as u64,
+				pre_hash: metadata.pre_hash,
+				difficulty: metadata.difficulty,
+			}));
+		}
+
+		tokio::time::sleep(POLL_INTERVAL).await;
+	}
+}
+
+/// Read the next line from `reader`, without its line ending.
+///
+/// `buffer` holds the bytes of a line read so far, so that reading can resume after being
+/// cancelled. Lines longer than [`MAX_LINE_LENGTH`] are an `InvalidData` error.
+async fn next_line<R: AsyncBufRead + Unpin>(
+	reader: &mut R,
+	buffer: &mut Vec<u8>,
+) -> io::Result<Option<String>> {
+	let limit = (MAX_LINE_LENGTH + 1).saturating_sub(buffer.len());
+	(&mut *reader).take(limit as u64).read_until(b'\n', buffer).await?;
+
+	if buffer.last() != Some(&b'\n') {
+		if buffer.len() > MAX_LINE_LENGTH {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "Line too long"))
+		}
+		if buffer.is_empty() {
+			return Ok(None)
+		}
+	}
+
+	let line = String::from_utf8(std::mem::take(buffer))
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+	Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()))
+}
+
+struct Connection<S, H> {
+	shared: Arc<Shared<S, H>>,
+	extranonce: u64,
+	subscribed: bool,
+	accepted: u64,
+	/// The job shares were last submitted for.
+	shares_job: u64,
+	/// Nonces of the shares accepted for `shares_job`.
+	shares: HashSet<U256>,
+}
+
+impl<S: MiningSource + Sync, H: PowHasher> Connection<S, H> {
+	fn new(shared: Arc<Shared<S, H>>, extranonce: u64) -> Self {
+		Self {
+			shared,
+			extranonce,
+			subscribed: false,
+			accepted: 0,
+			shares_job: 0,
+			shares: HashSet::new(),
+		}
+	}
+
+	async fn run(mut self, stream: TcpStream) {
+		let (reader, mut writer) = stream.into_split();
+		let mut reader = BufReader::new(reader);
+		let mut buffer = Vec::new();
+		let mut jobs = self.shared.jobs.clone();
+
+		loop {
+			tokio::select! {
+				line = next_line(&mut reader, &mut buffer) => match line {
+					Ok(Some(line)) => {
+						let was_subscribed = self.subscribed;
+						let response = self.handle(&line).await;
+						if send(&mut writer, &response).await.is_err() {
+							break
+						}
+						// Hand newly subscribed miners the current job right away.
+						if !was_subscribed &&
+							self.subscribed && self.notify(&mut writer, &mut jobs).await.is_err()
+						{
+							break
+						}
+					},
+					Ok(None) => break,
+					Err(e) => {
+						debug!(
+							target: LOG_TARGET,
+							"Dropping miner with extranonce {}: {}",
+							self.extranonce,
+							e,
+						);
+						break
+					},
+				},
+				changed = jobs.changed(), if self.subscribed => {
+					if changed.is_err() || self.notify(&mut writer, &mut jobs).await.is_err() {
+						break
+					}
+				},
+			}
+		}
+
+		debug!(
+			target: LOG_TARGET,
+			"Miner with extranonce {} disconnected after {} accepted shares",
+			self.extranonce,
+			self.accepted,
+		);
+	}
+
+	async fn notify(
+		&self,
+		writer: &mut OwnedWriteHalf,
+		jobs: &mut watch::Receiver<Option<Job>>,
+	) -> std::io::Result<()> {
+		let job = jobs.borrow_and_update().clone();
+		match job {
+			Some(job) => {
+				let params = Notify {
+					job_id: job.id,
+					pre_hash: job.pre_hash,
+					difficulty: job.difficulty,
+					share_difficulty: share_difficulty(job.difficulty, self.shared.share_divisor),
+					extranonce: self.extranonce,
+				};
+				send(writer, &json!({ "id": null, "method": "mining.notify", "params": params }))
+					.await
+			},
+			None => Ok(()),
+		}
+	}
+
+	async fn handle(&mut self, line: &str) -> Value {
+		let request: Request = match serde_json::from_str(line) {
+			Ok(request) => request,
+			Err(e) => return response(Value::Null, Err(format!("Parse error: {}", e))),
+		};
+
+		let result = match request.method.as_str() {
+			"mining.subscribe" => {
+				self.subscribed = true;
+				Ok(json!({ "extranonce": self.extranonce, "extranonceBits": 64 }))
+			},
+			"mining.authorize" => Ok(Value::Bool(true)),
+			"mining.submit" => self.submit(request.params).await,
+			method => Err(format!("Unknown method: {}", method)),
+		};
+
+		response(request.id, result)
+	}
+
+	async fn submit(&mut self, params: Value) -> Result<Value, String> {
+		let (job_id, nonce): (u64, U256) =
+			serde_json::from_value(params).map_err(|e| format!("Invalid params: {}", e))?;
+
+		let job = self.shared.jobs.borrow().clone();
+		let job = match job {
+			Some(job) if job.id == job_id => job,
+			_ => return Err(ShareError::StaleJob.message().into()),
+		};
+
+		if self.shares_job != job.id {
+			self.shares_job = job.id;
+			self.shares.clear();
+		}
+		if self.shares.contains(&nonce) {
+			return Err(ShareError::Duplicate.message().into())
+		}
+
+		let seal = check_share(
+			&job,
+			self.extranonce,
+			nonce,
+			self.shared.share_divisor,
+			self.shared.hasher.clone(),
+		)
+		.map_err(|e| e.message().to_string())?;
+		self.shares.insert(nonce);
+		self.accepted += 1;
+
+		if let Some(seal) = seal {
+			let source = self.shared.source.clone();
+			let imported = tokio::task::spawn_blocking(move || source.submit(seal.encode()))
+				.await
+				.unwrap_or(false);
+			info!(
+				target: LOG_TARGET,
+				"Block found by miner with extranonce {} (imported: {})",
+				self.extranonce,
+				imported,
+			);
+		}
+
+		Ok(Value::Bool(true))
+	}
+}
+
+fn response(id: Value, result: Result<Value, String>) -> Value {
+	match result {
+		Ok(result) => json!({ "id": id, "result": result, "error": null }),
+		Err(error) => json!({ "id": id, "result": null, "error": error }),
+	}
+}
+
+async fn send(writer: &mut OwnedWriteHalf, message: &Value) -> std::io::Result<()> {
+	let mut line = message.to_string();
+	line.push('\n');
+	writer.write_all(line.as_bytes()).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Blake2bHasher;
+	use parity_scale_codec::Decode;
+	use sc_consensus_pow::MiningMetadata;
+	use sp_consensus_pow::Seal as RawSeal;
+	use std::sync::{atomic::AtomicUsize, Mutex};
+	use tokio::io::Lines;
+
+	#[derive(Clone, Default)]
+	struct TestSource {
+		version: Arc<AtomicUsize>,
+		metadata: Arc<Mutex<Option<MiningMetadata<H256, U256>>>>,
+		submitted: Arc<Mutex<Vec<RawSeal>>>,
+	}
+
+	impl MiningSource for TestSource {
+		fn version(&self) -> usize {
+			self.version.load(Ordering::SeqCst)
+		}
+
+		fn metadata(&self) -> Option<MiningMetadata<H256, U256>> {
+			self.metadata.lock().unwrap().clone()
+		}
+
+		fn submit(&self, seal: RawSeal) -> bool {
+			if self.metadata.lock().unwrap().take().is_none() {
+				return false
+			}
+			self.submitted.lock().unwrap().push(seal);
+			self.version.fetch_add(1, Ordering::SeqCst);
+			true
+		}
+	}
+
+	fn job(difficulty: U256) -> Job {
+		Job { id: 0, pre_hash: H256::repeat_byte(7), difficulty }
+	}
+
+	fn nonce(extranonce: u64, low: u64) -> U256 {
+		(U256::from(extranonce) << EXTRANONCE_SHIFT) + U256::from(low)
+	}
+
+	#[test]
+	fn share_difficulty_is_never_zero() {
+		assert_eq!(share_difficulty(U256::from(1000), 10), U256::from(100));
+		assert_eq!(share_difficulty(U256::from(5), 10), U256::one());
+		assert_eq!(share_difficulty(U256::from(5), 0), U256::from(5));
+	}
+
+	#[test]
+	fn rejects_nonces_of_other_connections() {
+		assert_eq!(
+			check_share(&job(U256::one()), 1, nonce(2, 0), 1, Blake2bHasher),
+			Err(ShareError::ForeignNonce)
+		);
+		assert!(check_share(&job(U256::one()), 2, nonce(2, 0), 1, Blake2bHasher).is_ok());
+	}
+
+	#[test]
+	fn rejects_shares_below_share_difficulty() {
+		assert_eq!(
+			check_share(&job(U256::MAX), 1, nonce(1, 0), 1, Blake2bHasher),
+			Err(ShareError::LowDifficulty)
+		);
+	}
+
+	#[test]
+	fn only_shares_meeting_block_difficulty_yield_a_seal() {
+		// The share difficulty is one, so every share is valid but almost none is a block.
+		let share =
+			check_share(&job(U256::from(u64::MAX)), 1, nonce(1, 0), u64::MAX, Blake2bHasher);
+		assert_eq!(share, Ok(None));
+
+		let block = check_share(&job(U256::one()), 1, nonce(1, 0), 1, Blake2bHasher)
+			.unwrap()
+			.unwrap();
+		assert_eq!(block.nonce, nonce(1, 0));
+		assert_eq!(block.difficulty, U256::one());
+	}
+
+	#[tokio::test]
+	async fn lines_longer_than_the_maximum_are_refused() {
+		let mut input = b"{}\r\n".to_vec();
+		input.extend(vec![b' '; MAX_LINE_LENGTH]);
+		input.push(b'\n');
+		input.extend(vec![b' '; MAX_LINE_LENGTH + 1]);
+		input.push(b'\n');
+		let (mut reader, mut buffer) = (&input[..], Vec::new());
+
+		assert_eq!(next_line(&mut reader, &mut buffer).await.unwrap().unwrap(), "{}");
+		assert_eq!(
+			next_line(&mut reader, &mut buffer).await.unwrap().unwrap().len(),
+			MAX_LINE_LENGTH
+		);
+		assert_eq!(
+			next_line(&mut reader, &mut buffer).await.unwrap_err().kind(),
+			io::ErrorKind::InvalidData
+		);
+
+		let (mut reader, mut buffer) = (&b"last"[..], Vec::new());
+		assert_eq!(next_line(&mut reader, &mut buffer).await.unwrap().unwrap(), "last");
+		assert_eq!(next_line(&mut reader, &mut buffer).await.unwrap(), None);
+	}
+
+	async fn read(lines: &mut Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>) -> Value {
+		let line = tokio::time::timeout(Duration::from_secs(10), lines.next_line())
+			.await
+			.expect("server did not answer in time")
+			.unwrap()
+			.unwrap();
+		serde_json::from_str(&line).unwrap()
+	}
+
+	#[tokio::test]
+	async fn miners_receive_jobs_and_submit_blocks() {
+		let source = TestSource::default();
+		*source.metadata.lock().unwrap() = Some(MiningMetadata {
+			best_hash: H256::zero(),
+			pre_hash: H256::repeat_byte(7),
+			pre_runtime: None,
+			difficulty: U256::one(),
+		});
+
+		let config =
+			StratumConfig { listen_addr: "127.0.0.1:0".parse().unwrap(), share_divisor: 1 };
+		let server = StratumServer::bind(config, source.clone(), Blake2bHasher).unwrap();
+		let addr = server.local_addr().unwrap();
+		tokio::spawn(server.run());
+
+		let (reader, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+		let mut lines = BufReader::new(reader).lines();
+
+		// Let the server pick up the block template before subscribing.
+		tokio::time::sleep(2 * POLL_INTERVAL).await;
+		writer.write_all(b"{\"id\":1,\"method\":\"mining.subscribe\"}\n").await.unwrap();
+		let subscribed = read(&mut lines).await;
+		assert_eq!(subscribed["id"], 1);
+		let extranonce = subscribed["result"]["extranonce"].as_u64().unwrap();
+
+		let notify = read(&mut lines).await;
+		assert_eq!(notify["method"], "mining.notify");
+		assert_eq!(notify["params"]["extranonce"], extranonce);
+		let job_id = notify["params"]["jobId"].as_u64().unwrap();
+
+		let submit =
+			json!({ "id": 2, "method": "mining.submit", "params": [job_id, nonce(extranonce, 0)] });
+		writer.write_all(format!("{}\n", submit).as_bytes()).await.unwrap();
+		let accepted = read(&mut lines).await;
+		assert_eq!(accepted["result"], true, "{}", accepted);
+
+		let submitted = source.submitted.lock().unwrap();
+		assert_eq!(submitted.len(), 1);
+		assert_eq!(Seal::decode(&mut &submitted[0][..]).unwrap().nonce, nonce(extranonce, 0));
+	}
+
+	/// Start a server over `source` and subscribe a miner, returning its connection, extranonce
+	/// and first job.
+	async fn subscribe(
+		source: TestSource,
+		share_divisor: u64,
+	) -> (Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>, OwnedWriteHalf, u64, u64) {
+		let config = StratumConfig { listen_addr: "127.0.0.1:0".parse().unwrap(), share_divisor };
+		let server = StratumServer::bind(config, source, Blake2bHasher).unwrap();
+		let addr = server.local_addr().unwrap();
+		tokio::spawn(server.run());
+
+		let (reader, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+		let mut lines = BufReader::new(reader).lines();
+
+		// Let the server pick up the block template before subscribing.
+		tokio::time::sleep(2 * POLL_INTERVAL).await;
+		writer.write_all(b"{\"id\":1,\"method\":\"mining.subscribe\"}\n").await.unwrap();
+		let extranonce = read(&mut lines).await["result"]["extranonce"].as_u64().unwrap();
+		let job_id = read(&mut lines).await["params"]["jobId"].as_u64().unwrap();
+
+		(lines, writer, extranonce, job_id)
+	}
+
+	#[tokio::test]
+	async fn shares_are_only_accepted_once_per_job() {
+		let source = TestSource::default();
+		// Every share meets the share difficulty, but hardly any is a block.
+		*source.metadata.lock().unwrap() = Some(MiningMetadata {
+			best_hash: H256::zero(),
+			pre_hash: H256::repeat_byte(7),
+			pre_runtime: None,
+			difficulty: U256::from(u64::MAX),
+		});
+		let (mut lines, mut writer, extranonce, job_id) = subscribe(source, u64::MAX).await;
+
+		let submit =
+			json!({ "id": 2, "method": "mining.submit", "params": [job_id, nonce(extranonce, 0)] });
+		writer.write_all(format!("{}\n", submit).as_bytes()).await.unwrap();
+		assert_eq!(read(&mut lines).await["result"], true);
+
+		writer.write_all(format!("{}\n", submit).as_bytes()).await.unwrap();
+		assert_eq!(read(&mut lines).await["error"], ShareError::Duplicate.message());
+	}
+
+	#[tokio::test]
+	async fn connections_sending_long_lines_are_closed() {
+		let source = TestSource::default();
+		*source.metadata.lock().unwrap() = Some(MiningMetadata {
+			best_hash: H256::zero(),
+			pre_hash: H256::repeat_byte(7),
+			pre_runtime: None,
+			difficulty: U256::one(),
+		});
+		let (mut lines, mut writer, _, _) = subscribe(source, 1).await;
+
+		writer.write_all(&vec![b' '; MAX_LINE_LENGTH + 1]).await.unwrap();
+		let closed = tokio::time::timeout(Duration::from_secs(10), lines.next_line())
+			.await
+			.expect("server did not close the connection in time");
+		assert!(!matches!(closed, Ok(Some(_))), "{:?}", closed);
+	}
+}
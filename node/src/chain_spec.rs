@@ -1,17 +1,61 @@
 use node_template_runtime::{
 	AccountId, BalancesConfig, GenesisConfig, Signature, SudoConfig,
 	SystemConfig, WASM_BINARY, opaque::SessionKeys, SessionConfig, AuthorityDiscoveryConfig,
+	DifficultyConfig, PoiConfig, PoiParametersConfig,
 };
+use pow::PowHasherKind;
+use sc_chain_spec::{ChainSpecExtension, ChainSpecGroup};
 use sc_service::ChainType;
+use serde::{Deserialize, Serialize};
+use poi::PoiId;
 use sp_authority_discovery::AuthorityId as AuthorityDiscoveryId;
-use sp_core::{sr25519, Pair, Public};
+use sp_core::{sr25519, Pair, Public, U256};
 use sp_runtime::traits::{IdentifyAccount, Verify};
 
+/// Difficulty the chain starts mining at, before the first retarget.
+const INITIAL_DIFFICULTY: u64 = 100;
+
+/// Confirmations before finality on development chains, kept low to see blocks finalized quickly.
+const DEV_FINALITY_CONFIRMATIONS: u32 = 10;
+
 // The URL for the telemetry server.
 // const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
 
+/// Node-side consensus parameters recorded in the chain spec, so that every node of a chain
+/// agrees on them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ChainSpecExtension, ChainSpecGroup)]
+#[serde(rename_all = "camelCase")]
+pub struct Extensions {
+	/// The hash function used to compute and verify Proof-of-Work seals.
+	#[serde(default)]
+	pub pow_hasher: PowHasherKind,
+	/// Number of blocks mined on top of a block before the node finalizes it.
+	#[serde(default = "default_finality_confirmations")]
+	pub finality_confirmations: u32,
+}
+
+fn default_finality_confirmations() -> u32 {
+	pow::finality::DEFAULT_CONFIRMATIONS
+}
+
+impl Default for Extensions {
+	fn default() -> Self {
+		Self {
+			pow_hasher: PowHasherKind::default(),
+			finality_confirmations: default_finality_confirmations(),
+		}
+	}
+}
+
+impl Extensions {
+	/// Try to get the extension from the given `ChainSpec`.
+	pub fn try_get(chain_spec: &dyn sc_service::ChainSpec) -> Option<&Self> {
+		sc_chain_spec::get_extension(chain_spec.extensions())
+	}
+}
+
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate ChainSpec type.
-pub type ChainSpec = sc_service::GenericChainSpec<GenesisConfig>;
+pub type ChainSpec = sc_service::GenericChainSpec<GenesisConfig, Extensions>;
 
 /// Generate a crypto pair from seed.
 pub fn get_from_seed<TPublic: Public>(seed: &str) -> <TPublic::Pair as Pair>::Public {
@@ -30,16 +74,17 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
-pub fn authority_keys_from_seed(s: &str) -> (AccountId, AuthorityDiscoveryId) {
+pub fn authority_keys_from_seed(s: &str) -> (AccountId, AuthorityDiscoveryId, PoiId) {
 	println!("{} authority key : {:?}", s, get_account_id_from_seed::<sr25519::Public>(s));
 	(
 		get_account_id_from_seed::<sr25519::Public>(s),
 		get_from_seed::<AuthorityDiscoveryId>(s),
+		get_from_seed::<PoiId>(s),
 	)
 }
 
-fn session_keys(authority_discovery: AuthorityDiscoveryId) -> SessionKeys {
-	SessionKeys { authority_discovery }
+fn session_keys(authority_discovery: AuthorityDiscoveryId, poi: PoiId) -> SessionKeys {
+	SessionKeys { authority_discovery, poi }
 }
 
 pub fn development_config() -> Result<ChainSpec, String> {
@@ -76,7 +121,10 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		// Properties
 		None,
 		// Extensions
-		None,
+		Extensions {
+			pow_hasher: PowHasherKind::Sha3,
+			finality_confirmations: DEV_FINALITY_CONFIRMATIONS,
+		},
 	))
 }
 
@@ -114,18 +162,22 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 		None,
 		// Protocol ID
 		Some("amb"),
-		// Properties
+		// Fork ID
 		None,
+		// Properties
 		None,
 		// Extensions
-		None,
+		Extensions {
+			pow_hasher: PowHasherKind::Sha3,
+			finality_confirmations: pow::finality::DEFAULT_CONFIRMATIONS,
+		},
 	))
 }
 
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	wasm_binary: &[u8],
-	initial_authorities: Vec<(AccountId, AuthorityDiscoveryId)>,
+	initial_authorities: Vec<(AccountId, AuthorityDiscoveryId, PoiId)>,
 	root_key: AccountId,
 	endowed_accounts: Vec<AccountId>,
 	_enable_println: bool,
@@ -146,7 +198,7 @@ fn testnet_genesis(
 				(
 					x.0.clone(),
 					x.0.clone(),
-					session_keys(x.1.clone()),
+					session_keys(x.1.clone(), x.2.clone()),
 				)
 			})
 			.collect::<Vec<_>>(),
@@ -154,6 +206,15 @@ fn testnet_genesis(
 		authority_discovery: AuthorityDiscoveryConfig {
 			keys: vec![],
 		},
+		poi: PoiConfig {
+			keys: vec![],
+		},
+		poi_parameters: PoiParametersConfig {
+			parameters: Default::default(),
+		},
+		difficulty: DifficultyConfig {
+			initial_difficulty: U256::from(INITIAL_DIFFICULTY),
+		},
 		sudo: SudoConfig {
 			// Assign network admin rights.
 			key: Some(root_key),
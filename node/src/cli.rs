@@ -7,6 +7,22 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	/// Account credited with the rewards of the blocks mined by this node, in SS58 format.
+	#[arg(long, value_name = "SS58")]
+	pub author: Option<node_template_runtime::AccountId>,
+
+	/// Number of threads used to mine blocks when running as an authority.
+	#[arg(long, default_value_t = 1)]
+	pub mining_threads: usize,
+
+	/// Serve work to external miners over a Stratum-like TCP protocol on this address.
+	#[arg(long, value_name = "ADDR")]
+	pub stratum_addr: Option<std::net::SocketAddr>,
+
+	/// Shares submitted over Stratum must meet the block difficulty divided by this value.
+	#[arg(long, default_value_t = 256)]
+	pub stratum_share_divisor: u64,
 }
 
 #[derive(Debug, clap::Subcommand)]
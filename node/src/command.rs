@@ -207,10 +207,18 @@ pub fn run() -> sc_cli::Result<()> {
 		},
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
+			let mining = service::MiningParams {
+				threads: cli.mining_threads,
+				stratum: cli.stratum_addr.map(|listen_addr| pow::stratum::StratumConfig {
+					listen_addr,
+					share_divisor: cli.stratum_share_divisor,
+				}),
+				author: cli.author.clone(),
+			};
 			runner.run_node_until_exit(|config| async move {
 				match config.role {
 					// Role::Full => service::new_light(config),
-					_ => service::new_full(config),
+					_ => service::new_full(config, mining),
 				}
 				.map_err(sc_cli::Error::Service)
 			})
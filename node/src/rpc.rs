@@ -18,7 +18,7 @@ pub use sc_rpc_api::DenyUnsafe;
 use sp_keystore::SyncCryptoStorePtr;
 
 /// Full client dependencies.
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, M> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
@@ -26,12 +26,16 @@ pub struct FullDeps<C, P> {
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
 	/// Keystore
-	pub keystore: SyncCryptoStorePtr
+	pub keystore: SyncCryptoStorePtr,
+	/// Handle to the PoW mining worker, if this node authors blocks
+	pub mining: Option<M>,
+	/// Confirmations after which blocks are finalized
+	pub finality_confirmations: u32,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(
-	deps: FullDeps<C, P>,
+pub fn create_full<C, P, M>(
+	deps: FullDeps<C, P, M>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
 C: ProvideRuntimeApi<Block>,
@@ -40,19 +44,27 @@ C: Send + Sync + 'static,
 C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 C::Api: pallet_template_rpc::TemplateRuntimeApi<Block>,
+C::Api: pallet_poi_runtime_api::PoiApi<Block>,
+C::Api: pallet_poi_parameters_runtime_api::PoiParametersApi<Block>,
 C::Api: BlockBuilder<Block>,
 P: TransactionPool + 'static,
+M: pow::MiningSource + Sync,
 {
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 	use pallet_template_rpc::{TemplatePallet, TemplateApiServer};
+	use pow::rpc::{Finality, FinalityApiServer, Mining, MiningApiServer};
+	use poi::rpc::{Poi, PoiApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe, keystore } = deps;
+	let FullDeps { client, pool, deny_unsafe, keystore, mining, finality_confirmations } = deps;
 
 	module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
 	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
-	module.merge(TemplatePallet::new(client, keystore).into_rpc())?;
+	module.merge(TemplatePallet::new(client.clone()).into_rpc())?;
+	module.merge(Poi::new(client, keystore, deny_unsafe).into_rpc())?;
+	module.merge(Mining::new(mining, deny_unsafe).into_rpc())?;
+	module.merge(Finality::new(finality_confirmations).into_rpc())?;
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
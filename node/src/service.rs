@@ -1,22 +1,16 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
 use async_trait::async_trait;
-use futures::{executor::block_on, StreamExt};
-use node_template_runtime::{self, opaque::Block, RuntimeApi};
+use futures::StreamExt;
+use node_template_runtime::{self, opaque::Block, AccountId, RuntimeApi};
 use pow::*;
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_keystore::LocalKeystore;
 use sc_network::{Event, NetworkEventStream};
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
-use sp_api::{Encode, ProvideRuntimeApi};
-use sp_authority_discovery::AuthorityDiscoveryApi;
-use sp_core::{sr25519, U256, H512};
-use sp_keystore::SyncCryptoStore;
-use sp_runtime::{
-	key_types::AUTHORITY_DISCOVERY as AUTHORITY_DISCOVERY_KEY_TYPE, traits::Block as BlockT,
-};
-use std::{sync::Arc, thread, time::Duration};
+use sp_runtime::{codec::Encode, traits::Block as BlockT};
+use std::{sync::Arc, time::Duration};
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -41,7 +35,7 @@ impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
 pub(crate) type FullClient =
 	sc_service::TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<ExecutorDispatch>>;
 type FullBackend = sc_service::TFullBackend<Block>;
-type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
+type FullSelectChain = pow::HeaviestChain<FullBackend, Block>;
 
 pub struct CreateInherentDataProviders;
 
@@ -58,6 +52,20 @@ impl sp_inherents::CreateInherentDataProviders<Block, ()> for CreateInherentData
 	}
 }
 
+/// The Proof-of-Work hash function recorded in the chain spec, Sha3 if the spec predates it.
+fn pow_hasher(config: &Configuration) -> PowHasherKind {
+	crate::chain_spec::Extensions::try_get(&*config.chain_spec)
+		.map(|extensions| extensions.pow_hasher)
+		.unwrap_or_default()
+}
+
+/// Confirmations after which blocks are finalized, as recorded in the chain spec.
+fn finality_confirmations(config: &Configuration) -> u32 {
+	crate::chain_spec::Extensions::try_get(&*config.chain_spec)
+		.map(|extensions| extensions.finality_confirmations)
+		.unwrap_or(pow::finality::DEFAULT_CONFIRMATIONS)
+}
+
 pub fn new_partial(
 	config: &Configuration,
 ) -> Result<
@@ -73,7 +81,7 @@ pub fn new_partial(
 				Arc<FullClient>,
 				FullClient,
 				FullSelectChain,
-				MinimalSha3Algorithm<FullClient>,
+				Sha3Algorithm<FullClient, PowHasherKind>,
 				CreateInherentDataProviders,
 			>,
 			Option<Telemetry>,
@@ -116,7 +124,7 @@ pub fn new_partial(
 		telemetry
 	});
 
-	let select_chain = sc_consensus::LongestChain::new(backend.clone());
+	let select_chain = pow::HeaviestChain::new(backend.clone());
 
 	let transaction_pool = sc_transaction_pool::BasicPool::new_full(
 		config.transaction_pool.clone(),
@@ -129,7 +137,7 @@ pub fn new_partial(
 	// let can_author_with =
 	// sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
 
-	let pow_algorithm = pow::MinimalSha3Algorithm::new(client.clone());
+	let pow_algorithm = pow::Sha3Algorithm::new(client.clone(), pow_hasher(config));
 
 	let pow_block_import = sc_consensus_pow::PowBlockImport::new(
 		client.clone(),
@@ -167,8 +175,18 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 	Err("Remote Keystore not supported.")
 }
 
+/// How an authority node mines blocks.
+pub struct MiningParams {
+	/// Number of native mining threads.
+	pub threads: usize,
+	/// Settings of the Stratum server for external miners, if one should be started.
+	pub stratum: Option<pow::stratum::StratumConfig>,
+	/// Account credited with the rewards of the mined blocks.
+	pub author: Option<AccountId>,
+}
+
 /// Builds a new service for a full client.
-pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(mut config: Configuration, mining: MiningParams) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -191,6 +209,17 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		};
 	}
 
+	// Authorities answer the signing requests of the Proof-of-Interaction tours over the network.
+	if config.role.is_authority() {
+		let signer = poi::protocol::ChallengeSigner::new(
+			poi::protocol::ClientView::new(client.clone()),
+			keystore_container.sync_keystore(),
+		);
+		let (handler, protocol_config) = poi::protocol::SignRequestHandler::new(signer);
+		config.network.request_response_protocols.push(protocol_config);
+		task_manager.spawn_handle().spawn("poi-sign", Some("poi"), handler.run());
+	}
+
 	let (network, system_rpc_tx, tx_handler_controller, network_starter, sync_service) =
 		sc_service::build_network(sc_service::BuildNetworkParams {
 			config: &config,
@@ -212,16 +241,71 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 	}
 
 	let role = config.role.clone();
+	let pow_hasher = pow_hasher(&config);
+	let finality_confirmations = finality_confirmations(&config);
 	let prometheus_registry = config.prometheus_registry().cloned();
 
+	// The mining worker is built before the RPC extensions so that external miners can be
+	// handed work through the `mining_*` RPC methods.
+	let mining_worker = if role.is_authority() {
+		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
+			task_manager.spawn_handle(),
+			client.clone(),
+			transaction_pool.clone(),
+			prometheus_registry.as_ref(),
+			telemetry.as_ref().map(|x| x.handle()),
+		);
+
+		let pow_algorithm = pow::Sha3Algorithm::new(client.clone(), pow_hasher);
+
+		// The author is embedded in the pre-runtime digest of every block, to be rewarded.
+		if mining.author.is_none() {
+			log::warn!("⛏  No --author given, mined blocks will not be rewarded");
+		}
+		let pre_runtime = mining.author.as_ref().map(Encode::encode);
+
+		let (worker, worker_task) = sc_consensus_pow::start_mining_worker(
+			Box::new(pow_block_import),
+			client.clone(),
+			select_chain,
+			pow_algorithm.clone(),
+			proposer_factory,
+			sync_service.clone(),
+			sync_service.clone(),
+			pre_runtime,
+			CreateInherentDataProviders,
+			// time to wait for a new block before starting to mine a new one
+			Duration::from_secs(10),
+			// how long to take to actually build the block (i.e. executing extrinsics)
+			Duration::from_secs(10),
+		);
+
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"pow",
+			Some("block-authoring"),
+			worker_task,
+		);
+
+		Some(worker)
+	} else {
+		None
+	};
+
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
 		let keystore = keystore_container.sync_keystore();
+		let mining_worker = mining_worker.clone();
 
 		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe, keystore: keystore.clone() };
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				keystore: keystore.clone(),
+				mining: mining_worker.clone(),
+				finality_confirmations,
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -241,15 +325,14 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		telemetry: telemetry.as_mut(),
 	})?;
 
-	if role.is_authority() {
-		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
-			task_manager.spawn_handle(),
-			client.clone(),
-			transaction_pool,
-			prometheus_registry.as_ref(),
-			telemetry.as_ref().map(|x| x.handle()),
-		);
+	// Finalize blocks once they are buried deep enough on the best chain.
+	task_manager.spawn_handle().spawn(
+		"pow-finality",
+		Some("finality"),
+		pow::finality::run_finality::<_, FullBackend, _>(client.clone(), finality_confirmations),
+	);
 
+	if role.is_authority() {
 		let dht_event_stream =
 			network.event_stream("authority-discovery").filter_map(|e| async move {
 				match e {
@@ -258,7 +341,7 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 				}
 			});
 
-		let (mut _discovery_worker, mut _discovery_service) =
+		let (mut _discovery_worker, _discovery_service) =
 			sc_authority_discovery::new_worker_and_service(
 				client.clone(),
 				network.clone(),
@@ -272,101 +355,27 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 			Some("peer-discovery"),
 			Box::pin(_discovery_worker.run()),
 		);
+	}
 
-		let pow_algorithm = pow::MinimalSha3Algorithm::new(client.clone());
-
-		let (_worker, worker_task) = sc_consensus_pow::start_mining_worker(
-			Box::new(pow_block_import),
-			client.clone(),
-			select_chain,
-			pow_algorithm.clone(),
-			proposer_factory,
-			sync_service.clone(),
-			sync_service.clone(),
-			None,
-			CreateInherentDataProviders,
-			// time to wait for a new block before starting to mine a new one
-			Duration::from_secs(10),
-			// how long to take to actually build the block (i.e. executing extrinsics)
-			Duration::from_secs(10),
-		);
-
-		task_manager.spawn_essential_handle().spawn_blocking(
-			"pow",
-			Some("block-authoring"),
-			worker_task,
-		);
-
-		// Get node authority-discovery public session key from keystore
-		let authority_discovery_pubkey: Vec<sr25519::Public> = SyncCryptoStore::sr25519_public_keys(
-			&*keystore_container.sync_keystore(),
-			AUTHORITY_DISCOVERY_KEY_TYPE,
-		)
-		.iter()
-		.map(|k| sr25519::Public::from(k.clone()))
-		.collect();
-
-		// Use authority-discovery session key to sign a message (should use a different ECDSA session key KEY_TYPE instead)
-		let signature = SyncCryptoStore::sign_with(
-			&*keystore_container.sync_keystore(),
-			AUTHORITY_DISCOVERY_KEY_TYPE,
-			&authority_discovery_pubkey[0].into(),
-			"My Signed Message".as_bytes()
-		).unwrap();
-
-		match signature {
-			Some(sig) => println!("Signature: {:?}", H512::from_slice(&sig)),
-			_ => {},
-		};
-
-		// Start Mining
-		let mut nonce: U256 = U256::from(0);
-		thread::spawn(move || loop {
-			let worker = _worker.clone();
-			let metadata = worker.metadata();
-
-			if let Some(metadata) = metadata {
-				// Get the list of authorities from autority-discovery pallet at a specific bloc
-				let mut authorities =
-					client.clone().runtime_api().authorities(metadata.best_hash).unwrap();
-
-				let authorities_len = authorities.len();
-
-				// Sort authorities to have the same order accross nodes
-				authorities.sort();
-
-				// Print number of authorities
-				println!("[Authorities] (length = {})", authorities_len);
-
-				// Print all authorities libp2p multiaddr with local node filtered out
-				for (i, authority) in authorities.iter().filter(|&x| *x != authority_discovery_pubkey[0].into()).enumerate() {
-					println!(
-						"Authority {} : {:?} - {:?}",
-						i,
-						block_on(
-							_discovery_service.get_addresses_by_authority_id(authority.clone())
-						),
-						authority.clone()
-					);
-				}
-
-				let compute =
-					Compute { difficulty: metadata.difficulty, pre_hash: metadata.pre_hash, nonce };
-				let seal = compute.compute();
-				if hash_meets_difficulty(&seal.work, seal.difficulty) {
-					nonce = U256::from(0);
-					block_on(worker.submit(seal.encode()));
-				} else {
-					nonce = nonce.saturating_add(U256::from(1));
-					if nonce == U256::MAX {
-						nonce = U256::from(0);
-					}
-				}
-				thread::sleep(Duration::new(1, 0));
-			} else {
-				thread::sleep(Duration::new(1, 0));
-			}
-		});
+	// Start mining on the blocks built by the mining worker
+	if let Some(worker) = mining_worker {
+		if let Some(stratum) = mining.stratum {
+			let listen_addr = stratum.listen_addr;
+			let server = pow::stratum::StratumServer::bind(stratum, worker.clone(), pow_hasher)
+				.map_err(|e| {
+					ServiceError::Other(format!(
+						"Failed to bind Stratum server to {}: {}",
+						listen_addr, e
+					))
+				})?;
+			task_manager.spawn_handle().spawn("stratum", Some("block-authoring"), server.run());
+		}
+
+		let miner = pow::Miner::new(worker, mining.threads, pow_hasher)
+			.start()
+			.map_err(|e| ServiceError::Other(format!("Failed to spawn mining threads: {}", e)))?;
+		// The mining threads are stopped along with the node
+		task_manager.keep_alive(miner);
 	}
 
 	network_starter.start_network();
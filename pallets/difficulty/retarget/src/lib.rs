@@ -0,0 +1,129 @@
+//! Retargeting of a difficulty from the timestamps of the blocks, shared by the pallets whose
+//! difficulty follows the block time.
+//!
+//! Blocks are grouped into retarget windows of a fixed number of blocks. When a window closes,
+//! the difficulty is scaled by how much faster or slower than expected its blocks came, see
+//! [`advance`] and [`retarget`].
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_arithmetic::{
+	traits::{AtLeast32Bit, AtLeast32BitUnsigned, Saturating, UniqueSaturatedInto, Zero},
+	FixedPointNumber, FixedU64,
+};
+use sp_core::U256;
+
+/// A difficulty that can be scaled by the ratio of two timespans.
+pub trait Scale {
+	/// `self * numerator / denominator`, saturating instead of overflowing. `denominator` is
+	/// never zero.
+	fn scale(self, numerator: u64, denominator: u64) -> Self;
+}
+
+impl Scale for U256 {
+	fn scale(self, numerator: u64, denominator: u64) -> Self {
+		self.saturating_mul(U256::from(numerator)) / U256::from(denominator)
+	}
+}
+
+impl Scale for FixedU64 {
+	fn scale(self, numerator: u64, denominator: u64) -> Self {
+		let scaled =
+			u128::from(self.into_inner()) * u128::from(numerator) / u128::from(denominator);
+		FixedU64::from_inner(scaled.min(u128::from(u64::MAX)) as u64)
+	}
+}
+
+/// Scale `current` after `actual` milliseconds were spent producing blocks that were expected to
+/// take `expected` milliseconds, so that blocks come at the expected pace again.
+///
+/// The observed timespan is clamped to `[expected / max_factor, expected * max_factor]` so a
+/// single retarget can never move `current` by more than `max_factor` in either direction.
+pub fn retarget<D: Scale>(current: D, actual: u64, expected: u64, max_factor: u32) -> D {
+	let max_factor = u64::from(max_factor.max(1));
+	let lower = (expected / max_factor).max(1);
+	let upper = expected.saturating_mul(max_factor).max(lower);
+
+	current.scale(expected, actual.clamp(lower, upper))
+}
+
+/// What a block does to the current retarget window.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Window<Moment> {
+	/// No window was open yet: the block opens the first one.
+	Opened,
+	/// The block falls within the open window.
+	Pending,
+	/// The block closes the window, which took `timespan` milliseconds while its blocks were
+	/// expected to take `expected` milliseconds, and opens the next one.
+	Closed { timespan: Moment, expected: u64 },
+}
+
+/// What block `number`, stamped `now`, does to the retarget window opened by the block number
+/// and timestamp of `opened`, windows closing every `interval` blocks that should each take
+/// `target_block_time` milliseconds.
+///
+/// Unless the block is [`Window::Pending`], the window now opens at `(number, now)`.
+pub fn advance<BlockNumber, Moment>(
+	opened: Option<(BlockNumber, Moment)>,
+	number: BlockNumber,
+	now: Moment,
+	interval: BlockNumber,
+	target_block_time: u64,
+) -> Window<Moment>
+where
+	BlockNumber: AtLeast32BitUnsigned + Copy,
+	Moment: AtLeast32Bit + Copy,
+{
+	// The first timestamp we ever see only opens the initial retarget window.
+	let (start, last) = match opened {
+		Some(window) => window,
+		None => return Window::Opened,
+	};
+
+	if interval.is_zero() || !(number % interval).is_zero() || number <= start {
+		return Window::Pending
+	}
+
+	let blocks: u64 = number.saturating_sub(start).unique_saturated_into();
+	Window::Closed {
+		timespan: now.saturating_sub(last),
+		expected: target_block_time.saturating_mul(blocks),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn adjustment_is_clamped() {
+		assert_eq!(retarget(U256::from(1_000), 1, 60_000, 4), U256::from(4_000));
+		assert_eq!(retarget(U256::from(1_000), 1_000_000, 60_000, 4), U256::from(250));
+	}
+
+	#[test]
+	fn any_difficulty_is_scaled_alike() {
+		assert_eq!(retarget(U256::from(1_000), 30_000, 60_000, 4), U256::from(2_000));
+		assert_eq!(
+			retarget(FixedU64::saturating_from_integer(1_000), 30_000, 60_000, 4),
+			FixedU64::saturating_from_integer(2_000)
+		);
+		assert_eq!(
+			retarget(FixedU64::from_inner(u64::MAX), 1, 60_000, 4),
+			FixedU64::from_inner(u64::MAX)
+		);
+	}
+
+	#[test]
+	fn windows_close_every_interval() {
+		assert_eq!(advance::<u32, u64>(None, 1, 6_000, 10, 6_000), Window::Opened);
+		assert_eq!(advance(Some((1u32, 6_000u64)), 9, 54_000, 10, 6_000), Window::Pending);
+		assert_eq!(
+			advance(Some((1u32, 6_000u64)), 10, 36_000, 10, 6_000),
+			Window::Closed { timespan: 30_000, expected: 54_000 }
+		);
+		assert_eq!(advance(Some((10u32, 6_000u64)), 10, 36_000, 10, 6_000), Window::Pending);
+		assert_eq!(advance(Some((1u32, 6_000u64)), 10, 36_000, 0, 6_000), Window::Pending);
+	}
+}
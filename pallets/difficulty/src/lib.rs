@@ -0,0 +1,134 @@
+//! Stores the current Proof-of-Work difficulty and retargets it every `RetargetInterval` blocks
+//! so that the average block time converges towards `TargetBlockTime`.
+//!
+//! The pallet is driven by `pallet_timestamp`: plug it in as the timestamp pallet's
+//! `OnTimestampSet` hook and every timestamp inherent will be accounted for.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use pallet_difficulty_retarget::retarget;
+use sp_core::U256;
+
+/// Compute the difficulty that should replace `current` after `actual` milliseconds were spent
+/// producing blocks that were expected to take `expected` milliseconds.
+///
+/// The difficulty is [`retarget`]ed, and never falls below `minimum`.
+pub fn next_difficulty(
+	current: U256,
+	actual: u64,
+	expected: u64,
+	max_factor: u32,
+	minimum: U256,
+) -> U256 {
+	retarget(current, actual, expected, max_factor).max(minimum)
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{pallet_prelude::*, traits::OnTimestampSet};
+	use pallet_difficulty_retarget::{advance, Window};
+	use sp_core::U256;
+	use sp_runtime::traits::UniqueSaturatedInto;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_timestamp::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The average block time, in milliseconds, the difficulty is retargeted towards.
+		#[pallet::constant]
+		type TargetBlockTime: Get<u64>;
+
+		/// Number of blocks between two difficulty adjustments.
+		#[pallet::constant]
+		type RetargetInterval: Get<Self::BlockNumber>;
+
+		/// Maximum factor by which a single adjustment may raise or lower the difficulty.
+		#[pallet::constant]
+		type MaxAdjustmentFactor: Get<u32>;
+
+		/// The difficulty never drops below this value.
+		#[pallet::constant]
+		type MinimumDifficulty: Get<U256>;
+	}
+
+	/// The difficulty the next block must be sealed with.
+	#[pallet::storage]
+	#[pallet::getter(fn difficulty)]
+	pub type CurrentDifficulty<T> = StorageValue<_, U256, ValueQuery>;
+
+	/// Block number and timestamp of the block that opened the current retarget window.
+	#[pallet::storage]
+	#[pallet::getter(fn last_retarget)]
+	pub type LastRetarget<T: Config> = StorageValue<_, (T::BlockNumber, T::Moment)>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The difficulty was retargeted. [old, new, timespan]
+		DifficultyAdjusted { old: U256, new: U256, timespan: T::Moment },
+	}
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub initial_difficulty: U256,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { initial_difficulty: U256::from(100) }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			CurrentDifficulty::<T>::put(self.initial_difficulty.max(T::MinimumDifficulty::get()));
+		}
+	}
+
+	impl<T: Config> OnTimestampSet<T::Moment> for Pallet<T> {
+		fn on_timestamp_set(now: T::Moment) {
+			let number = frame_system::Pallet::<T>::block_number();
+			let window = advance(
+				LastRetarget::<T>::get(),
+				number,
+				now,
+				T::RetargetInterval::get(),
+				T::TargetBlockTime::get(),
+			);
+
+			match window {
+				Window::Pending => {},
+				Window::Opened => LastRetarget::<T>::put((number, now)),
+				Window::Closed { timespan, expected } => {
+					let old = CurrentDifficulty::<T>::get();
+					let new = crate::next_difficulty(
+						old,
+						timespan.unique_saturated_into(),
+						expected,
+						T::MaxAdjustmentFactor::get(),
+						T::MinimumDifficulty::get(),
+					);
+
+					CurrentDifficulty::<T>::put(new);
+					LastRetarget::<T>::put((number, now));
+
+					Self::deposit_event(Event::DifficultyAdjusted { old, new, timespan });
+				},
+			}
+		}
+	}
+}
@@ -0,0 +1,102 @@
+use crate as pallet_difficulty;
+use frame_support::{
+	parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64},
+};
+use sp_core::{H256, U256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+/// Block time the mock runtime retargets towards, in milliseconds.
+pub const TARGET_BLOCK_TIME: u64 = 6000;
+/// Number of blocks in a retarget window.
+pub const RETARGET_INTERVAL: u64 = 10;
+/// Difficulty the mock chain starts with.
+pub const INITIAL_DIFFICULTY: u64 = 1_000;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system,
+		Timestamp: pallet_timestamp,
+		Difficulty: pallet_difficulty,
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = Difficulty;
+	type MinimumPeriod = ConstU64<1>;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub MinimumDifficulty: U256 = U256::from(10);
+}
+
+impl pallet_difficulty::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type TargetBlockTime = ConstU64<TARGET_BLOCK_TIME>;
+	type RetargetInterval = ConstU64<RETARGET_INTERVAL>;
+	type MaxAdjustmentFactor = ConstU32<4>;
+	type MinimumDifficulty = MinimumDifficulty;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	GenesisConfig {
+		system: Default::default(),
+		difficulty: pallet_difficulty::GenesisConfig {
+			initial_difficulty: U256::from(INITIAL_DIFFICULTY),
+		},
+	}
+	.build_storage()
+	.unwrap()
+	.into()
+}
+
+/// Produce blocks up to and including `n`, spacing their timestamps `block_time` apart.
+pub fn run_to_block(n: u64, block_time: u64) {
+	while System::block_number() < n {
+		let next = System::block_number() + 1;
+		System::set_block_number(next);
+		Timestamp::set_timestamp(next * block_time);
+	}
+}
@@ -0,0 +1,70 @@
+use crate::{mock::*, next_difficulty, Event};
+use sp_core::U256;
+
+#[test]
+fn genesis_sets_initial_difficulty() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY));
+		assert_eq!(Difficulty::last_retarget(), None);
+	});
+}
+
+#[test]
+fn difficulty_is_unchanged_inside_a_window() {
+	new_test_ext().execute_with(|| {
+		run_to_block(RETARGET_INTERVAL - 1, TARGET_BLOCK_TIME / 2);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY));
+		assert_eq!(Difficulty::last_retarget(), Some((1, TARGET_BLOCK_TIME / 2)));
+	});
+}
+
+#[test]
+fn fast_blocks_raise_difficulty() {
+	new_test_ext().execute_with(|| {
+		run_to_block(RETARGET_INTERVAL, TARGET_BLOCK_TIME / 2);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY * 2));
+		System::assert_last_event(
+			Event::DifficultyAdjusted {
+				old: U256::from(INITIAL_DIFFICULTY),
+				new: U256::from(INITIAL_DIFFICULTY * 2),
+				timespan: (RETARGET_INTERVAL - 1) * TARGET_BLOCK_TIME / 2,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn slow_blocks_lower_difficulty() {
+	new_test_ext().execute_with(|| {
+		run_to_block(RETARGET_INTERVAL, TARGET_BLOCK_TIME * 2);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY / 2));
+		assert_eq!(
+			Difficulty::last_retarget(),
+			Some((RETARGET_INTERVAL, RETARGET_INTERVAL * TARGET_BLOCK_TIME * 2))
+		);
+	});
+}
+
+#[test]
+fn retargets_every_interval() {
+	new_test_ext().execute_with(|| {
+		run_to_block(RETARGET_INTERVAL, TARGET_BLOCK_TIME / 2);
+		run_to_block(2 * RETARGET_INTERVAL, TARGET_BLOCK_TIME / 2);
+		assert_eq!(Difficulty::difficulty(), U256::from(INITIAL_DIFFICULTY * 4));
+	});
+}
+
+#[test]
+fn adjustment_is_clamped() {
+	assert_eq!(next_difficulty(U256::from(1_000), 1, 60_000, 4, U256::one()), U256::from(4_000));
+	assert_eq!(
+		next_difficulty(U256::from(1_000), 1_000_000, 60_000, 4, U256::one()),
+		U256::from(250)
+	);
+}
+
+#[test]
+fn difficulty_never_drops_below_minimum() {
+	assert_eq!(next_difficulty(U256::from(20), 240_000, 60_000, 4, U256::from(10)), U256::from(10));
+}
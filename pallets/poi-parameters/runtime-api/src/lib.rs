@@ -0,0 +1,12 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use poi_core::PoiParameters;
+
+// Here we declare the runtime API. It is implemented in the `impl` block in
+// runtime file (the `runtime/src/lib.rs`)
+sp_api::decl_runtime_apis! {
+	pub trait PoiParametersApi {
+		/// The parameters of the tours sealing the children of the block.
+		fn parameters() -> PoiParameters;
+	}
+}
@@ -0,0 +1,178 @@
+//! Holds the parameters of Proof-of-Interaction tours: the size of the subset of services, the
+//! standard deviation and minimum of the tour length, and the difficulty, that is the mean
+//! tour length.
+//!
+//! The node reads them at the parent block through the `PoiParametersApi` runtime API, both to
+//! generate and to check proofs. They only change through root, within the bounds of
+//! [`PoiParameters`], except for the difficulty: as tours take about as long as they have
+//! hops, it is retargeted every `RetargetInterval` blocks so that the average block time
+//! converges towards `TargetBlockTime`.
+//!
+//! The pallet is driven by `pallet_timestamp`: plug it in as the timestamp pallet's
+//! `OnTimestampSet` hook and every timestamp inherent will be accounted for. The difficulty is
+//! retargeted with `pallet_difficulty_retarget`, the way `pallet_difficulty` retargets the
+//! Proof-of-Work difficulty.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use pallet_difficulty_retarget::retarget;
+use poi_core::FixedU64;
+pub use poi_core::PoiParameters;
+
+/// Compute the difficulty, the mean tour length, that should replace `current` after `actual`
+/// milliseconds were spent producing blocks that were expected to take `expected` milliseconds.
+///
+/// The difficulty is [`retarget`]ed, and stays within the bounds of [`PoiParameters`].
+pub fn next_difficulty(current: FixedU64, actual: u64, expected: u64, max_factor: u32) -> FixedU64 {
+	retarget(current, actual, expected, max_factor)
+		.clamp(PoiParameters::MIN.difficulty, PoiParameters::MAX.difficulty)
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::{pallet_prelude::*, traits::OnTimestampSet};
+	use frame_system::pallet_prelude::*;
+	use pallet_difficulty_retarget::{advance, Window};
+	use sp_runtime::traits::UniqueSaturatedInto;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_timestamp::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The average block time, in milliseconds, the difficulty is retargeted towards.
+		#[pallet::constant]
+		type TargetBlockTime: Get<u64>;
+
+		/// Number of blocks between two difficulty adjustments.
+		#[pallet::constant]
+		type RetargetInterval: Get<Self::BlockNumber>;
+
+		/// Maximum factor by which a single adjustment may raise or lower the difficulty.
+		#[pallet::constant]
+		type MaxAdjustmentFactor: Get<u32>;
+
+		/// Maximum number of authorities, and thus of nodes in the network tours are drawn from.
+		/// The minimum tour length of the parameters must fit in a proof for such a network.
+		#[pallet::constant]
+		type MaxAuthorities: Get<u32>;
+	}
+
+	/// The parameters of the tours sealing the children of the current block.
+	#[pallet::storage]
+	#[pallet::getter(fn parameters)]
+	pub type Parameters<T> = StorageValue<_, PoiParameters, ValueQuery>;
+
+	/// Block number and timestamp of the block that opened the current retarget window.
+	#[pallet::storage]
+	#[pallet::getter(fn last_retarget)]
+	pub type LastRetarget<T: Config> = StorageValue<_, (T::BlockNumber, T::Moment)>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The parameters were replaced. [old, new]
+		ParametersSet { old: PoiParameters, new: PoiParameters },
+		/// The difficulty was retargeted. [old, new, timespan]
+		DifficultyAdjusted { old: FixedU64, new: FixedU64, timespan: T::Moment },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// A parameter lies outside of its bounds.
+		OutOfBounds,
+	}
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub parameters: PoiParameters,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { parameters: PoiParameters::default() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			assert!(
+				self.parameters.is_valid_for(T::MaxAuthorities::get()),
+				"Genesis PoI parameters are out of bounds!"
+			);
+			Parameters::<T>::put(self.parameters);
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Replace the parameters of the tours, which must all lie within their bounds, the
+		/// minimum tour length of a network of `MaxAuthorities` nodes not exceeding the hops a
+		/// proof can hold.
+		///
+		/// The origin must be root.
+		#[pallet::call_index(0)]
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1).ref_time())]
+		pub fn set_parameters(origin: OriginFor<T>, parameters: PoiParameters) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(parameters.is_valid_for(T::MaxAuthorities::get()), Error::<T>::OutOfBounds);
+
+			let old = Parameters::<T>::get();
+			Parameters::<T>::put(parameters);
+
+			Self::deposit_event(Event::ParametersSet { old, new: parameters });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> OnTimestampSet<T::Moment> for Pallet<T> {
+		fn on_timestamp_set(now: T::Moment) {
+			let number = frame_system::Pallet::<T>::block_number();
+			let window = advance(
+				LastRetarget::<T>::get(),
+				number,
+				now,
+				T::RetargetInterval::get(),
+				T::TargetBlockTime::get(),
+			);
+
+			match window {
+				Window::Pending => {},
+				Window::Opened => LastRetarget::<T>::put((number, now)),
+				Window::Closed { timespan, expected } => {
+					let mut parameters = Parameters::<T>::get();
+					let old = parameters.difficulty;
+					parameters.difficulty = crate::next_difficulty(
+						old,
+						timespan.unique_saturated_into(),
+						expected,
+						T::MaxAdjustmentFactor::get(),
+					);
+
+					Parameters::<T>::put(parameters);
+					LastRetarget::<T>::put((number, now));
+
+					Self::deposit_event(Event::DifficultyAdjusted {
+						old,
+						new: parameters.difficulty,
+						timespan,
+					});
+				},
+			}
+		}
+	}
+}
@@ -0,0 +1,158 @@
+use crate::{mock::*, next_difficulty, Error, Event, PoiParameters as Parameters};
+use frame_support::{assert_noop, assert_ok};
+use poi_core::{FixedPointNumber, FixedU64, TourLengthDistribution};
+use sp_runtime::DispatchError;
+
+fn parameters() -> Parameters {
+	Parameters {
+		subset_size: 10,
+		std_deviation_coefficient: FixedU64::saturating_from_rational(1, 5),
+		min_tour_length: FixedU64::saturating_from_rational(3, 2),
+		difficulty: FixedU64::saturating_from_integer(40),
+		distribution: TourLengthDistribution::Geometric,
+	}
+}
+
+#[test]
+fn genesis_sets_parameters() {
+	new_test_ext(parameters()).execute_with(|| {
+		assert_eq!(PoiParameters::parameters(), parameters());
+	});
+}
+
+#[test]
+#[should_panic(expected = "Genesis PoI parameters are out of bounds!")]
+fn genesis_parameters_must_be_within_bounds() {
+	new_test_ext(Parameters { subset_size: 0, ..Default::default() });
+}
+
+#[test]
+fn root_sets_parameters() {
+	new_test_ext(Default::default()).execute_with(|| {
+		assert_ok!(PoiParameters::set_parameters(RuntimeOrigin::root(), parameters()));
+
+		assert_eq!(PoiParameters::parameters(), parameters());
+		System::assert_last_event(
+			Event::ParametersSet { old: Default::default(), new: parameters() }.into(),
+		);
+	});
+}
+
+#[test]
+fn only_root_sets_parameters() {
+	new_test_ext(Default::default()).execute_with(|| {
+		assert_noop!(
+			PoiParameters::set_parameters(RuntimeOrigin::signed(1), parameters()),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn parameters_must_be_within_bounds() {
+	new_test_ext(Default::default()).execute_with(|| {
+		let too_long = Parameters {
+			difficulty: FixedU64::from_inner(Parameters::MAX.difficulty.into_inner() + 1),
+			..parameters()
+		};
+
+		assert_noop!(
+			PoiParameters::set_parameters(RuntimeOrigin::root(), too_long),
+			Error::<Test>::OutOfBounds
+		);
+		assert_ok!(PoiParameters::set_parameters(RuntimeOrigin::root(), Parameters::MAX));
+		assert_ok!(PoiParameters::set_parameters(RuntimeOrigin::root(), Parameters::MIN));
+	});
+}
+
+#[test]
+fn minimum_tour_length_must_fit_the_largest_network() {
+	new_test_ext(Default::default()).execute_with(|| {
+		MaxAuthorities::set(512);
+		// 2 hops per node make MAX_HOPS over 512 nodes, the next 1/512 one more
+		let fits =
+			Parameters { min_tour_length: FixedU64::saturating_from_integer(2), ..parameters() };
+		let too_long = Parameters { min_tour_length: FixedU64::from_inner(2_001_953_125), ..fits };
+
+		assert_noop!(
+			PoiParameters::set_parameters(RuntimeOrigin::root(), too_long),
+			Error::<Test>::OutOfBounds
+		);
+		assert_noop!(
+			PoiParameters::set_parameters(RuntimeOrigin::root(), Parameters::MAX),
+			Error::<Test>::OutOfBounds
+		);
+		assert_ok!(PoiParameters::set_parameters(RuntimeOrigin::root(), fits));
+
+		MaxAuthorities::set(511);
+		assert_ok!(PoiParameters::set_parameters(RuntimeOrigin::root(), too_long));
+	});
+}
+
+fn difficulty(hops: u64) -> FixedU64 {
+	FixedU64::saturating_from_integer(hops)
+}
+
+#[test]
+fn difficulty_is_unchanged_inside_a_window() {
+	new_test_ext(parameters()).execute_with(|| {
+		run_to_block(RETARGET_INTERVAL - 1, TARGET_BLOCK_TIME / 2);
+		assert_eq!(PoiParameters::parameters(), parameters());
+		assert_eq!(PoiParameters::last_retarget(), Some((2, TARGET_BLOCK_TIME)));
+	});
+}
+
+#[test]
+fn fast_blocks_raise_difficulty() {
+	new_test_ext(parameters()).execute_with(|| {
+		run_to_block(RETARGET_INTERVAL, TARGET_BLOCK_TIME / 2);
+		assert_eq!(
+			PoiParameters::parameters(),
+			Parameters { difficulty: difficulty(80), ..parameters() }
+		);
+		System::assert_last_event(
+			Event::DifficultyAdjusted {
+				old: difficulty(40),
+				new: difficulty(80),
+				timespan: (RETARGET_INTERVAL - 2) * TARGET_BLOCK_TIME / 2,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn slow_blocks_lower_difficulty() {
+	new_test_ext(parameters()).execute_with(|| {
+		run_to_block(RETARGET_INTERVAL, TARGET_BLOCK_TIME * 2);
+		assert_eq!(PoiParameters::parameters().difficulty, difficulty(20));
+		assert_eq!(
+			PoiParameters::last_retarget(),
+			Some((RETARGET_INTERVAL, RETARGET_INTERVAL * TARGET_BLOCK_TIME * 2))
+		);
+	});
+}
+
+#[test]
+fn retargets_every_interval() {
+	new_test_ext(parameters()).execute_with(|| {
+		run_to_block(RETARGET_INTERVAL, TARGET_BLOCK_TIME / 2);
+		run_to_block(2 * RETARGET_INTERVAL, TARGET_BLOCK_TIME / 2);
+		assert_eq!(PoiParameters::parameters().difficulty, difficulty(160));
+	});
+}
+
+#[test]
+fn adjustment_is_clamped() {
+	assert_eq!(next_difficulty(difficulty(40), 1, 60_000, 4), difficulty(160));
+	assert_eq!(next_difficulty(difficulty(40), 1_000_000, 60_000, 4), difficulty(10));
+}
+
+#[test]
+fn difficulty_stays_within_bounds() {
+	assert_eq!(next_difficulty(difficulty(2), 240_000, 60_000, 4), Parameters::MIN.difficulty);
+	assert_eq!(
+		next_difficulty(Parameters::MAX.difficulty, 1, 60_000, 4),
+		Parameters::MAX.difficulty
+	);
+}
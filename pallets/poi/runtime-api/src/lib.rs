@@ -0,0 +1,15 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use poi_core::PoiId;
+use sp_authority_discovery::AuthorityId;
+use sp_std::vec::Vec;
+
+// Here we declare the runtime API. It is implemented in the `impl` block in
+// runtime file (the `runtime/src/lib.rs`)
+sp_api::decl_runtime_apis! {
+	pub trait PoiApi {
+		/// The authorities of the current session, each with the authority discovery key it is
+		/// reached through and the PoI key it signs hops with.
+		fn authorities() -> Vec<(AuthorityId, PoiId)>;
+	}
+}
@@ -0,0 +1,128 @@
+//! Keeps the PoI keys authorities sign Proof-of-Interaction hops with.
+//!
+//! The pallet is a session handler for the `poi_` key type. Like `pallet_authority_discovery`,
+//! it stores the keys of the current and next sessions in the order of the session validators,
+//! so the runtime can pair the PoI key of every authority with its authority discovery key.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use frame_support::{traits::OneSessionHandler, WeakBoundedVec};
+use poi_core::PoiId;
+use sp_std::prelude::*;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The maximum number of authorities whose keys are kept.
+		type MaxAuthorities: Get<u32>;
+	}
+
+	/// PoI keys of the current session, in the order of its validators.
+	#[pallet::storage]
+	pub type Keys<T: Config> =
+		StorageValue<_, WeakBoundedVec<PoiId, T::MaxAuthorities>, ValueQuery>;
+
+	/// PoI keys of the next session, in the order of its validators.
+	#[pallet::storage]
+	pub type NextKeys<T: Config> =
+		StorageValue<_, WeakBoundedVec<PoiId, T::MaxAuthorities>, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub keys: Vec<PoiId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { keys: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			Pallet::<T>::initialize_keys(&self.keys);
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// PoI keys of the current session.
+	pub fn current_authorities() -> WeakBoundedVec<PoiId, T::MaxAuthorities> {
+		Keys::<T>::get()
+	}
+
+	/// PoI keys of the next session.
+	pub fn next_authorities() -> WeakBoundedVec<PoiId, T::MaxAuthorities> {
+		NextKeys::<T>::get()
+	}
+
+	fn initialize_keys(keys: &[PoiId]) {
+		if keys.is_empty() {
+			return
+		}
+
+		assert!(Keys::<T>::get().is_empty(), "PoI keys are already initialized!");
+
+		let keys = WeakBoundedVec::<_, T::MaxAuthorities>::try_from(keys.to_vec())
+			.expect("Genesis holds more PoI keys than MaxAuthorities; qed");
+		Keys::<T>::put(&keys);
+		NextKeys::<T>::put(&keys);
+	}
+
+	fn bounded(keys: Vec<PoiId>) -> WeakBoundedVec<PoiId, T::MaxAuthorities> {
+		WeakBoundedVec::force_from(
+			keys,
+			Some(
+				"Warning: The session has more validators than expected. \
+				A runtime configuration adjustment may be needed.",
+			),
+		)
+	}
+}
+
+impl<T: Config> sp_runtime::BoundToRuntimeAppPublic for Pallet<T> {
+	type Public = PoiId;
+}
+
+impl<T: Config> OneSessionHandler<T::AccountId> for Pallet<T> {
+	type Key = PoiId;
+
+	fn on_genesis_session<'a, I: 'a>(authorities: I)
+	where
+		I: Iterator<Item = (&'a T::AccountId, Self::Key)>,
+	{
+		Self::initialize_keys(&authorities.map(|(_, key)| key).collect::<Vec<_>>());
+	}
+
+	fn on_new_session<'a, I: 'a>(changed: bool, validators: I, queued_validators: I)
+	where
+		I: Iterator<Item = (&'a T::AccountId, Self::Key)>,
+	{
+		// Keys are only replaced when the validators or their keys change, exactly as authority
+		// discovery does, so that both pallets always hold their keys in the same order.
+		if changed {
+			Keys::<T>::put(Self::bounded(validators.map(|(_, key)| key).collect()));
+		}
+
+		NextKeys::<T>::put(Self::bounded(queued_validators.map(|(_, key)| key).collect()));
+	}
+
+	fn on_disabled(_validator_index: u32) {}
+}
@@ -0,0 +1,71 @@
+use crate as pallet_poi;
+use frame_support::traits::{ConstU16, ConstU32, ConstU64};
+use poi_core::PoiId;
+use sp_core::{sr25519, H256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+/// Maximum number of authorities of the mock runtime.
+pub const MAX_AUTHORITIES: u32 = 4;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system,
+		Poi: pallet_poi,
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+impl pallet_poi::Config for Test {
+	type MaxAuthorities = ConstU32<MAX_AUTHORITIES>;
+}
+
+/// The PoI key of the authority `byte`.
+pub fn key(byte: u8) -> PoiId {
+	PoiId::from(sr25519::Public::from_raw([byte; 32]))
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext(keys: Vec<PoiId>) -> sp_io::TestExternalities {
+	GenesisConfig { system: Default::default(), poi: pallet_poi::GenesisConfig { keys } }
+		.build_storage()
+		.unwrap()
+		.into()
+}
@@ -0,0 +1,68 @@
+use crate::mock::*;
+use frame_support::traits::OneSessionHandler;
+
+/// Notify the pallet of a new session, `validators` and `queued` being account and key bytes.
+fn new_session(changed: bool, validators: &[(u64, u8)], queued: &[(u64, u8)]) {
+	let validators: Vec<_> = validators.iter().map(|(who, byte)| (who, key(*byte))).collect();
+	let queued: Vec<_> = queued.iter().map(|(who, byte)| (who, key(*byte))).collect();
+
+	Poi::on_new_session(changed, validators.into_iter(), queued.into_iter());
+}
+
+#[test]
+fn genesis_sets_current_and_next_keys() {
+	new_test_ext(vec![key(2), key(1)]).execute_with(|| {
+		assert_eq!(Poi::current_authorities().to_vec(), vec![key(2), key(1)]);
+		assert_eq!(Poi::next_authorities().to_vec(), vec![key(2), key(1)]);
+	});
+}
+
+#[test]
+fn genesis_session_sets_keys() {
+	new_test_ext(vec![]).execute_with(|| {
+		let validators = vec![(&1, key(3)), (&2, key(1))];
+		Poi::on_genesis_session(validators.into_iter());
+
+		assert_eq!(Poi::current_authorities().to_vec(), vec![key(3), key(1)]);
+		assert_eq!(Poi::next_authorities().to_vec(), vec![key(3), key(1)]);
+	});
+}
+
+#[test]
+fn keys_follow_the_validators_order() {
+	new_test_ext(vec![key(1)]).execute_with(|| {
+		new_session(true, &[(1, 3), (2, 1), (3, 2)], &[(2, 1), (1, 3)]);
+
+		assert_eq!(Poi::current_authorities().to_vec(), vec![key(3), key(1), key(2)]);
+		assert_eq!(Poi::next_authorities().to_vec(), vec![key(1), key(3)]);
+	});
+}
+
+#[test]
+fn current_keys_only_change_with_the_validators() {
+	new_test_ext(vec![key(1)]).execute_with(|| {
+		new_session(false, &[(1, 2)], &[(1, 3)]);
+
+		assert_eq!(Poi::current_authorities().to_vec(), vec![key(1)]);
+		assert_eq!(Poi::next_authorities().to_vec(), vec![key(3)]);
+	});
+}
+
+#[test]
+fn too_many_validators_are_kept() {
+	new_test_ext(vec![]).execute_with(|| {
+		let validators: Vec<_> =
+			(0..=MAX_AUTHORITIES as u8).map(|byte| (u64::from(byte), byte)).collect();
+		new_session(true, &validators, &validators);
+
+		assert_eq!(Poi::current_authorities().len(), MAX_AUTHORITIES as usize + 1);
+	});
+}
+
+#[test]
+#[should_panic(expected = "PoI keys are already initialized!")]
+fn keys_cannot_be_initialized_twice() {
+	new_test_ext(vec![key(1)]).execute_with(|| {
+		Poi::on_genesis_session(vec![(&1, key(2))].into_iter());
+	});
+}
@@ -0,0 +1,171 @@
+//! Pays the author of every block a subsidy, halved every `HalvingInterval` blocks, along with
+//! the transaction fees collected in the block.
+//!
+//! Miners embed their SCALE-encoded `AccountId` in the Proof-of-Work pre-runtime digest, which
+//! [`PowDigestAuthor`] reads back. Fees reach the pallet by plugging it in as the fee handler
+//! of `pallet_transaction_payment::CurrencyAdapter`; without an author they are burned.
+//!
+//! Payouts too small for the author's account to reach the existential deposit are not
+//! credited: subsidies are not minted and fees are burned. `Rewarded` only reports what the
+//! author was actually credited.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use codec::Decode;
+use core::marker::PhantomData;
+use frame_support::traits::FindAuthor;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, Zero},
+	ConsensusEngineId,
+};
+
+/// The subsidy paid once `initial` has been halved `halvings` times.
+pub fn block_subsidy<Balance: AtLeast32BitUnsigned>(initial: Balance, halvings: u32) -> Balance {
+	let mut subsidy = initial;
+
+	for _ in 0..halvings {
+		if subsidy.is_zero() {
+			break
+		}
+		subsidy /= 2u32.into();
+	}
+
+	subsidy
+}
+
+/// Finds the block author in the pre-runtime digest of the Proof-of-Work engine.
+pub struct PowDigestAuthor<AccountId>(PhantomData<AccountId>);
+
+impl<AccountId: Decode> FindAuthor<AccountId> for PowDigestAuthor<AccountId> {
+	fn find_author<'a, I>(digests: I) -> Option<AccountId>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		digests.into_iter().find_map(|(id, mut data)| {
+			if id == POW_ENGINE_ID {
+				AccountId::decode(&mut data).ok()
+			} else {
+				None
+			}
+		})
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Currency, FindAuthor, Imbalance, OnUnbalanced},
+	};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::{Saturating, UniqueSaturatedInto, Zero};
+
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+	pub type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+		<T as frame_system::Config>::AccountId,
+	>>::NegativeImbalance;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The currency rewards are minted in.
+		type Currency: Currency<Self::AccountId>;
+
+		/// Finds the author of the current block in its pre-runtime digests.
+		type FindAuthor: FindAuthor<Self::AccountId>;
+
+		/// The subsidy paid for each block before the first halving.
+		#[pallet::constant]
+		type InitialSubsidy: Get<BalanceOf<Self>>;
+
+		/// Number of blocks after which the subsidy is halved.
+		#[pallet::constant]
+		type HalvingInterval: Get<Self::BlockNumber>;
+	}
+
+	/// Author of the current block, if any. Cleared at the end of every block.
+	#[pallet::storage]
+	#[pallet::getter(fn author)]
+	pub type Author<T: Config> = StorageValue<_, T::AccountId>;
+
+	/// Fees paid to the author of the current block so far. Cleared at the end of every block.
+	#[pallet::storage]
+	#[pallet::getter(fn collected_fees)]
+	pub type CollectedFees<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The block author was rewarded. [author, subsidy, fees]
+		Rewarded { author: T::AccountId, subsidy: BalanceOf<T>, fees: BalanceOf<T> },
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_n: T::BlockNumber) -> Weight {
+			let digest = frame_system::Pallet::<T>::digest();
+			let pre_runtime_digests = digest.logs().iter().filter_map(|d| d.as_pre_runtime());
+
+			if let Some(author) = T::FindAuthor::find_author(pre_runtime_digests) {
+				Author::<T>::put(author);
+			}
+
+			// Also accounts for `on_finalize`, which takes both values and credits the author.
+			T::DbWeight::get().reads_writes(3, 4)
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			let fees = CollectedFees::<T>::take();
+
+			if let Some(author) = Author::<T>::take() {
+				// Nothing is credited when the author's account would not reach the existential
+				// deposit.
+				let subsidy = T::Currency::deposit_creating(&author, Self::subsidy(n)).peek();
+
+				if !subsidy.is_zero() || !fees.is_zero() {
+					Self::deposit_event(Event::Rewarded { author, subsidy, fees });
+				}
+			}
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The subsidy minted for block `number`.
+		pub fn subsidy(number: T::BlockNumber) -> BalanceOf<T> {
+			let interval = T::HalvingInterval::get();
+			let halvings =
+				if interval.is_zero() { 0 } else { (number / interval).unique_saturated_into() };
+
+			crate::block_subsidy(T::InitialSubsidy::get(), halvings)
+		}
+	}
+
+	impl<T: Config> OnUnbalanced<NegativeImbalanceOf<T>> for Pallet<T> {
+		fn on_nonzero_unbalanced(fees: NegativeImbalanceOf<T>) {
+			// Fees are dropped, and thereby burned, when the block has no known author.
+			if let Some(author) = Author::<T>::get() {
+				let credited = T::Currency::deposit_creating(&author, fees.peek());
+				CollectedFees::<T>::mutate(|collected| {
+					*collected = collected.saturating_add(credited.peek())
+				});
+				// Whatever could not be credited is burned along with the rest of the fees.
+				drop(fees.offset(credited));
+			}
+		}
+	}
+}
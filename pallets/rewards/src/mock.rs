@@ -0,0 +1,114 @@
+use crate as pallet_rewards;
+use codec::Encode;
+use frame_support::{
+	parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64, Hooks},
+};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage, Digest, DigestItem,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+/// Subsidy of the mock chain before the first halving.
+pub const INITIAL_SUBSIDY: u64 = 1_000;
+/// Number of blocks between two halvings.
+pub const HALVING_INTERVAL: u64 = 10;
+/// An account endowed at genesis, paying fees in tests.
+pub const PAYER: u64 = 2;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		Rewards: pallet_rewards,
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+parameter_types! {
+	pub static ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+impl pallet_rewards::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type FindAuthor = pallet_rewards::PowDigestAuthor<u64>;
+	type InitialSubsidy = ConstU64<INITIAL_SUBSIDY>;
+	type HalvingInterval = ConstU64<HALVING_INTERVAL>;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	GenesisConfig {
+		system: Default::default(),
+		balances: pallet_balances::GenesisConfig { balances: vec![(PAYER, 1_000_000)] },
+	}
+	.build_storage()
+	.unwrap()
+	.into()
+}
+
+/// Start block `number`, whose pre-runtime digest holds `author`.
+pub fn start_block(number: u64, author: Option<u64>) {
+	let mut digest = Digest::default();
+	if let Some(author) = author {
+		digest.push(DigestItem::PreRuntime(POW_ENGINE_ID, author.encode()));
+	}
+
+	System::initialize(&number, &Default::default(), &digest);
+	Rewards::on_initialize(number);
+}
+
+/// Finish the current block.
+pub fn finish_block() {
+	Rewards::on_finalize(System::block_number());
+}
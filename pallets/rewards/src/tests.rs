@@ -0,0 +1,115 @@
+use crate::{block_subsidy, mock::*, Event, PowDigestAuthor};
+use codec::Encode;
+use frame_support::traits::{
+	Currency, ExistenceRequirement, FindAuthor, OnUnbalanced, WithdrawReasons,
+};
+
+const AUTHOR: u64 = 1;
+
+/// Withdraw `amount` from the payer as a transaction fee and hand it to the pallet.
+fn pay_fee(amount: u64) {
+	let fee =
+		Balances::withdraw(&PAYER, amount, WithdrawReasons::FEE, ExistenceRequirement::KeepAlive)
+			.unwrap();
+	Rewards::on_unbalanced(fee);
+}
+
+#[test]
+fn subsidy_halves_every_interval() {
+	assert_eq!(block_subsidy(1_000u64, 0), 1_000);
+	assert_eq!(block_subsidy(1_000u64, 1), 500);
+	assert_eq!(block_subsidy(1_000u64, 3), 125);
+	assert_eq!(block_subsidy(1_000u64, 10), 0);
+	assert_eq!(block_subsidy(u128::MAX, u32::MAX), 0);
+
+	new_test_ext().execute_with(|| {
+		assert_eq!(Rewards::subsidy(1), INITIAL_SUBSIDY);
+		assert_eq!(Rewards::subsidy(HALVING_INTERVAL - 1), INITIAL_SUBSIDY);
+		assert_eq!(Rewards::subsidy(HALVING_INTERVAL), INITIAL_SUBSIDY / 2);
+		assert_eq!(Rewards::subsidy(2 * HALVING_INTERVAL), INITIAL_SUBSIDY / 4);
+	});
+}
+
+#[test]
+fn finds_author_in_pow_digest_only() {
+	let author = AUTHOR.encode();
+
+	assert_eq!(PowDigestAuthor::<u64>::find_author(vec![(*b"pow_", &author[..])]), Some(AUTHOR));
+	assert_eq!(PowDigestAuthor::<u64>::find_author(vec![(*b"aura", &author[..])]), None);
+	assert_eq!(PowDigestAuthor::<u64>::find_author(vec![(*b"pow_", &author[..2])]), None);
+}
+
+#[test]
+fn author_is_paid_the_subsidy() {
+	new_test_ext().execute_with(|| {
+		start_block(1, Some(AUTHOR));
+		assert_eq!(Rewards::author(), Some(AUTHOR));
+		finish_block();
+
+		assert_eq!(Balances::free_balance(AUTHOR), INITIAL_SUBSIDY);
+		assert_eq!(Rewards::author(), None);
+		System::assert_last_event(
+			Event::Rewarded { author: AUTHOR, subsidy: INITIAL_SUBSIDY, fees: 0 }.into(),
+		);
+
+		start_block(HALVING_INTERVAL, Some(AUTHOR));
+		finish_block();
+		assert_eq!(Balances::free_balance(AUTHOR), INITIAL_SUBSIDY + INITIAL_SUBSIDY / 2);
+	});
+}
+
+#[test]
+fn author_is_paid_the_fees() {
+	new_test_ext().execute_with(|| {
+		let issuance = Balances::total_issuance();
+
+		start_block(1, Some(AUTHOR));
+		pay_fee(30);
+		pay_fee(12);
+		assert_eq!(Rewards::collected_fees(), 42);
+		finish_block();
+
+		assert_eq!(Balances::free_balance(AUTHOR), INITIAL_SUBSIDY + 42);
+		assert_eq!(Balances::total_issuance(), issuance + INITIAL_SUBSIDY);
+		assert_eq!(Rewards::collected_fees(), 0);
+		System::assert_last_event(
+			Event::Rewarded { author: AUTHOR, subsidy: INITIAL_SUBSIDY, fees: 42 }.into(),
+		);
+	});
+}
+
+#[test]
+fn blocks_without_author_burn_fees_and_mint_nothing() {
+	new_test_ext().execute_with(|| {
+		let issuance = Balances::total_issuance();
+
+		start_block(1, None);
+		pay_fee(42);
+		finish_block();
+
+		assert_eq!(Balances::total_issuance(), issuance - 42);
+		assert_eq!(Balances::free_balance(AUTHOR), 0);
+		assert!(!System::events()
+			.iter()
+			.any(|record| matches!(record.event, RuntimeEvent::Rewards(_))));
+	});
+}
+
+#[test]
+fn payouts_below_existential_deposit_are_not_counted() {
+	new_test_ext().execute_with(|| {
+		ExistentialDeposit::set(INITIAL_SUBSIDY + 100);
+		let issuance = Balances::total_issuance();
+
+		start_block(1, Some(AUTHOR));
+		pay_fee(42);
+		assert_eq!(Rewards::collected_fees(), 0);
+		finish_block();
+
+		assert_eq!(Balances::free_balance(AUTHOR), 0);
+		assert_eq!(Balances::total_issuance(), issuance - 42);
+		assert!(!System::events()
+			.iter()
+			.any(|record| matches!(record.event, RuntimeEvent::Rewards(_))));
+	});
+}
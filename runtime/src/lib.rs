@@ -7,8 +7,9 @@
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
 use sp_api::impl_runtime_apis;
-use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata, U256};
 use sp_authority_discovery::AuthorityId as AuthorityDiscoveryId;
+use poi_core::PoiId;
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
 	traits::{
@@ -47,6 +48,18 @@ pub use sp_runtime::{Perbill, Permill};
 /// Import the template pallet.
 pub use pallet_template;
 
+/// Import the difficulty pallet.
+pub use pallet_difficulty;
+
+/// Import the rewards pallet.
+pub use pallet_rewards;
+
+/// Import the PoI pallet.
+pub use pallet_poi;
+
+/// Import the PoI parameters pallet.
+pub use pallet_poi_parameters;
+
 /// An index to a block.
 pub type BlockNumber = u32;
 
@@ -85,6 +98,7 @@ pub mod opaque {
 	impl_opaque_keys! {
 		pub struct SessionKeys {
 			pub authority_discovery: AuthorityDiscovery,
+			pub poi: Poi,
 		}
 	}
 }
@@ -208,7 +222,7 @@ parameter_types! {
 impl pallet_timestamp::Config for Runtime {
 	/// A timestamp: milliseconds since the unix epoch.
 	type Moment = u64;
-	type OnTimestampSet = ();
+	type OnTimestampSet = (Difficulty, PoiParameters);
 	type MinimumPeriod = MinimumPeriod;
 	type WeightInfo = ();
 }
@@ -216,6 +230,9 @@ impl pallet_timestamp::Config for Runtime {
 /// Existential deposit.
 pub const EXISTENTIAL_DEPOSIT: u128 = 500;
 
+/// One unit of the native token, which has 12 decimals.
+pub const UNIT: Balance = 1_000_000_000_000;
+
 impl pallet_balances::Config for Runtime {
 	type MaxLocks = ConstU32<50>;
 	type MaxReserves = ();
@@ -236,7 +253,8 @@ parameter_types! {
 
 impl pallet_transaction_payment::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
-	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+	/// Fees are paid to the block author.
+	type OnChargeTransaction = CurrencyAdapter<Balances, Rewards>;
 	type OperationalFeeMultiplier = ConstU8<5>;
 	type WeightToFee = IdentityFee<Balance>;
 	type LengthToFee = IdentityFee<Balance>;
@@ -252,6 +270,22 @@ impl pallet_authority_discovery::Config for Runtime {
 	type MaxAuthorities = ConstU32<100>;
 }
 
+/// Keeps the keys authorities sign PoI hops with, next to their authority discovery keys.
+impl pallet_poi::Config for Runtime {
+	type MaxAuthorities = ConstU32<100>;
+}
+
+/// Holds the parameters of PoI tours, set through sudo but for the difficulty, which follows
+/// the block time.
+impl pallet_poi_parameters::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type TargetBlockTime = ConstU64<MILLISECS_PER_BLOCK>;
+	/// Retarget the mean tour length every 10 minutes worth of blocks.
+	type RetargetInterval = ConstU32<{ 10 * MINUTES }>;
+	type MaxAdjustmentFactor = ConstU32<4>;
+	type MaxAuthorities = <Runtime as pallet_poi::Config>::MaxAuthorities;
+}
+
 parameter_types! {
 	pub const Period: u32 = 30;
 	pub const Offset: u32 = 0;
@@ -272,6 +306,29 @@ impl pallet_session::Config for Runtime {
 	type WeightInfo = pallet_session::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+	pub MinimumDifficulty: U256 = U256::from(100);
+}
+
+/// Configure the pallet-difficulty in pallets/difficulty.
+impl pallet_difficulty::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type TargetBlockTime = ConstU64<MILLISECS_PER_BLOCK>;
+	/// Retarget every 10 minutes worth of blocks.
+	type RetargetInterval = ConstU32<{ 10 * MINUTES }>;
+	type MaxAdjustmentFactor = ConstU32<4>;
+	type MinimumDifficulty = MinimumDifficulty;
+}
+
+/// Configure the pallet-rewards in pallets/rewards.
+impl pallet_rewards::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type FindAuthor = pallet_rewards::PowDigestAuthor<AccountId>;
+	type InitialSubsidy = ConstU128<{ 50 * UNIT }>;
+	/// Halve the subsidy every two years worth of blocks.
+	type HalvingInterval = ConstU32<{ 2 * 365 * DAYS }>;
+}
 
 /// Configure the pallet-template in pallets/template.
 impl pallet_template::Config for Runtime {
@@ -293,6 +350,10 @@ construct_runtime!(
 		Sudo: pallet_sudo,
 		Session: pallet_session,
 		AuthorityDiscovery: pallet_authority_discovery,
+		Poi: pallet_poi,
+		PoiParameters: pallet_poi_parameters,
+		Difficulty: pallet_difficulty,
+		Rewards: pallet_rewards,
 		// Include the custom logic from the pallet-template in the runtime.
 		TemplateModule: pallet_template,
 	}
@@ -421,6 +482,28 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_poi_runtime_api::PoiApi<Block> for Runtime {
+		fn authorities() -> Vec<(AuthorityDiscoveryId, PoiId)> {
+			// Both pallets hold the keys of the current session in the order of its validators.
+			AuthorityDiscovery::current_authorities()
+				.into_iter()
+				.zip(Poi::current_authorities())
+				.collect()
+		}
+	}
+
+	impl pallet_poi_parameters_runtime_api::PoiParametersApi<Block> for Runtime {
+		fn parameters() -> pallet_poi_parameters::PoiParameters {
+			PoiParameters::parameters()
+		}
+	}
+
+	impl sp_consensus_pow::DifficultyApi<Block, U256> for Runtime {
+		fn difficulty() -> U256 {
+			Difficulty::difficulty()
+		}
+	}
+
 	impl frame_system_rpc_runtime_api::AccountNonceApi<Block, AccountId, Index> for Runtime {
 		fn account_nonce(account: AccountId) -> Index {
 			System::account_nonce(account)